@@ -0,0 +1,950 @@
+use std::convert::TryInto;
+
+use crate::mandelbrot::big_fixed::BigFixed;
+use crate::mandelbrot::buddhabrot::*;
+use crate::mandelbrot::colour::{Colouring, Interior, Light};
+use crate::mandelbrot::escape::*;
+use crate::mandelbrot::expression::{Expression, parse_constant};
+use crate::mandelbrot::formula::*;
+use crate::mandelbrot::iteration_buffer::Channels;
+use crate::file::palette::read_palette;
+use crate::mandelbrot::mandelbrot::*;
+use crate::mandelbrot::newton::Newton;
+use crate::mandelbrot::palette::*;
+use crate::mandelbrot::parameters::Parameters;
+use crate::mandelbrot::real::Real;
+use crate::mandelbrot::viewport::*;
+
+pub const USAGE: &str = "\
+Usage: mandlebrot [OPTIONS]
+
+Render the Mandelbrot set to a png.
+
+Options:
+  -g, --generator <NAME>   Generator strategy [default: parallel3]
+  -a, --algorithm <NAME>   Escape algorithm [default: bulb-period]
+  -p, --precision <TYPE>   Float type used for the escape kernels: f32, f64, dd (double-double) [default: f32]
+  -c, --centre <X,Y>       Centre of the image on the complex plane
+  -z, --zoom <ZOOM>        Magnification about the centre, 1 shows -1..1 vertically
+  -b, --bounds <X0,X1,Y0,Y1>
+                           Explicit region to render instead of centre and zoom
+      --julia <X,Y>        Render the Julia set of c = X + Yi instead of the Mandelbrot set, the default
+                           region and centre move to the origin
+      --julia-pixel <PX,PY>
+                           Render the whole Julia set of the point at that pixel of the Mandelbrot set
+                           the region options describe
+  -f, --formula <NAME>     Set to render: mandelbrot, burning-ship, tricorn, celtic or buffalo, all but
+                           mandelbrot lack dz/dc for the derivative, distance and interior algorithms
+                           [default: mandelbrot]
+  -d, --exponent <D>       Render z^D + c: whole D from 2 multiply out, fractional and negative D go
+                           through the polar form in f64 [default: 2]
+  -e, --expression <FORMULA>
+                           Render a formula of z and c instead, e.g. 'z^3 - z + c' or 'sin(z)*c', from
+                           numbers, + - * / ^, i, pi, e and sin, cos, tan, sinh, cosh, tanh, exp, log,
+                           sqrt, abs, conj, re and im. Orbits start at z = c. Lacks dz/dc for the
+                           derivative, distance and interior algorithms
+      --newton-roots <LIST>
+                           Render Newton's method on the polynomial with these roots instead, comma
+                           separated and written as in --expression, e.g. '1, exp(2*pi*i/3), -0.5-0.866*i'.
+                           The default region and centre move to the origin
+      --newton-coefficients <LIST>
+                           As --newton-roots, from the coefficients highest power first, e.g. 1,0,0,-1
+                           for z^3 - 1
+      --aspect <MODE>      Fit explicit bounds to the image: stretch, fit, fill [default: stretch]
+  -i, --iterations <N>     Iteration limit before a point is treated as inside the set [default: 32768,
+                           buddhabrot and anti-buddhabrot: 1024]
+      --bailout <RADIUS>   Escape radius, raise it for more accurate smooth and distance colouring [default: 2]
+      --colouring <MODE>   banded, smooth (normalised iteration count), histogram or smooth-histogram
+                           (palette spread evenly over the pixels), distance (boundary line art, needs
+                           the distance, derivative or interior algorithm), trap (closest approach
+                           to --trap, needs the trap algorithm), tia (triangle inequality average) or
+                           stripe (stripe average), both need the average algorithm and look best with a
+                           large --bailout, or root (the root a Newton orbit settles on, darker the longer
+                           it takes) [default: banded, newton: root]
+      --trap <SHAPE>       Orbit trap: point:X,Y, line:X,Y,DEG, cross:X,Y, circle:X,Y,R or
+                           stalks:WIDTH (Pickover stalks on the axes) [default: point:0,0]
+      --stripe-density <N> Stripes per turn round the origin for stripe colouring [default: 5]
+      --lighting           Shade the exterior as a lit surface, embossing its colours (needs the
+                           distance, derivative or interior algorithm)
+      --light-angle <DEG>  Direction the light comes from, anticlockwise from the right [default: 45]
+      --light-height <H>   Height of the light, higher flattens the relief [default: 1.5]
+      --interior <MODE>    Points that never escape: black, distance (needs the interior algorithm),
+                           period (needs period, bulb-period, interior or newton), multiplier (attracting
+                           cycle, needs interior) or trap (as the colouring) [default: black]
+      --palette <PATH>     Gradient for all but banded colouring: Fractint .map, GIMP .ggr or a .toml stop list
+      --interpolation <SPACE>
+                           Blend palette stops in rgb (linear light) or oklab [default: oklab]
+      --palette-offset <F> Fraction of the palette to shift colours by [default: 0]
+      --palette-scale <N>  Smooth iterations per pass through the palette [default: 64, .map: its length]
+      --palette-repeat <MODE>
+                           Past the end of the palette: clamp, wrap, mirror [default: wrap, .ggr: mirror]
+  -W, --width <PIXELS>     Image width [default: 6144]
+  -H, --height <PIXELS>    Image height [default: 4096]
+  -o, --output <PATH>      Png file to write [default: res/mandelbrot.png]
+      --save-raw <PATH>    Also save the iteration counts, smooth values and any distances, to recolour later
+      --load-raw <PATH>    Colour a saved raw file instead of rendering, its region, iterations and
+                           algorithm replace the rendering options
+      --samples <N>        Random c whose orbits the density algorithms trace, per pixel of the image
+                           [default: 32]
+      --seed <N>           Seed for the density algorithms' random c, printed when left to chance
+      --nebulabrot-iterations <R,G,B>
+                           Iteration limits of the nebulabrot's red, green and blue channels
+                           [default: 5000,500,50]
+      --no-series          Iterate every perturbation pixel from zero instead of skipping ahead with a series
+      --benchmark          Time the generator instead of writing a file
+  -h, --help               Print this message
+
+Generators:
+  gms, half, cluster, cluster-simplified, cluster-checkered, parallel, parallel2,
+  parallel3, parallel-scoped, parallel-scoped-pixel, parallel-cluster
+
+Algorithms:
+  escape-time, bulb, period, bulb-period, derivative, distance, interior, trap, average
+  newton                   Newton's method until the orbit settles on a root, chosen by --newton-roots and
+                           --newton-coefficients
+  perturbation             Arbitrary precision reference orbit with f64 deltas for zooms past 1e-30,
+                           always schedules by row, ignores --precision and takes no --generator
+
+Density algorithms trace the orbits of random c in f64, ignoring --generator and --precision, and
+tone-map how often each pixel is visited:
+  buddhabrot               Orbits that escape
+  anti-buddhabrot          Orbits that never escape
+  nebulabrot               Buddhabrots to three iteration limits in the red, green and blue channels
+";
+
+pub const PERTURBATION: &str = "perturbation";
+pub const BUDDHABROT: &str = "buddhabrot";
+pub const ANTI_BUDDHABROT: &str = "anti-buddhabrot";
+pub const NEBULABROT: &str = "nebulabrot";
+
+// Keeps |z|^2 comfortably inside f32 and the integer limb of the perturbation reference
+const MAX_BAILOUT: f64 = 1e6;
+
+pub fn generators<T: Real, F: Formula, K: Kernel>() -> [(&'static str, Generator<T, F, K>); 11] {
+  [
+    ("gms", gms),
+    ("half", gms_half),
+    ("cluster", gms_cluster),
+    ("cluster-simplified", gms_cluster_simplified),
+    ("cluster-checkered", gms_cluster_checkered),
+    ("parallel", gms_parallel),
+    ("parallel2", gms_parallel2),
+    ("parallel3", gms_parallel3),
+    ("parallel-scoped", gms_parallel_scoped),
+    ("parallel-scoped-pixel", gms_parallel_scoped_pixel),
+    ("parallel-cluster", gms_parallel_cluster),
+  ]
+}
+
+pub fn formulas() -> [(&'static str, Fractal); 5] {
+  [
+    ("mandelbrot", Fractal::Mandelbrot),
+    ("burning-ship", Fractal::BurningShip),
+    ("tricorn", Fractal::Tricorn),
+    ("celtic", Fractal::Celtic),
+    ("buffalo", Fractal::Buffalo),
+  ]
+}
+
+// Algorithms whose results carry dz/dc
+const DERIVATIVE_ALGORITHMS: [&str; 3] = ["derivative", "distance", "interior"];
+// Algorithms that detect the period of interior points
+const PERIOD_ALGORITHMS: [&str; 4] = ["period", "bulb-period", "interior", "newton"];
+// Algorithms that analyse the attracting cycle of interior points
+const CYCLE_ALGORITHMS: [&str; 1] = ["interior"];
+// Algorithms that measure orbits against the orbit trap
+const TRAP_ALGORITHMS: [&str; 1] = ["trap"];
+// Algorithms that average terms along escaping orbits
+const AVERAGE_ALGORITHMS: [&str; 1] = ["average"];
+// Algorithms that find the root an orbit converges to
+const ROOT_ALGORITHMS: [&str; 1] = ["newton"];
+// Algorithms that count orbit points per pixel rather than iterating each pixel
+const DENSITY_ALGORITHMS: [&str; 3] = [BUDDHABROT, ANTI_BUDDHABROT, NEBULABROT];
+
+pub fn algorithms() -> [(&'static str, Algorithm); 10] {
+  [
+    ("escape-time", Algorithm::EscapeTime),
+    ("bulb", Algorithm::Bulb),
+    ("period", Algorithm::Period),
+    ("bulb-period", Algorithm::BulbPeriod),
+    ("derivative", Algorithm::Derivative),
+    ("distance", Algorithm::Distance),
+    ("interior", Algorithm::Interior),
+    ("trap", Algorithm::Trap),
+    ("average", Algorithm::Average),
+    ("newton", Algorithm::Newton),
+  ]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+  F32,
+  F64,
+  DoubleDouble,
+}
+
+// The set --formula names, each rendered with its own Formula type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+  // z^d + c, a Multibrot for d other than 2
+  Mandelbrot,
+  BurningShip,
+  Tricorn,
+  Celtic,
+  Buffalo,
+}
+
+pub struct Options {
+  pub generator: String,
+  pub algorithm: String,
+  pub precision: Precision,
+  pub region: Region,
+  pub aspect: Aspect,
+  pub width: usize,
+  pub height: usize,
+  pub max_iterations: u32,
+  pub bailout: f64,
+  pub julia: Option<(f64, f64)>,
+  pub formula: Fractal,
+  pub exponent: Exponent,
+  pub expression: Option<Expression>,
+  pub newton: Option<Newton>,
+  pub trap: Trap,
+  pub stripe_density: f64,
+  pub colouring: Colouring,
+  pub interior: Interior,
+  pub light: Option<Light>,
+  pub palette: Palette,
+  pub output: String,
+  pub save_raw: Option<String>,
+  pub load_raw: Option<String>,
+  pub series: bool,
+  // Per pixel
+  pub samples: u32,
+  pub seed: Option<u64>,
+  pub nebulabrot_iterations: [u32; 3],
+  pub benchmark: bool,
+}
+
+pub enum Command {
+  Help,
+  Render(Box<Options>),
+}
+
+impl Options {
+  pub fn generator<T: Real, F: Formula, K: Kernel>(&self) -> Generator<T, F, K> {
+    lookup(&generators(), "generator", &self.generator).expect("generator is validated while parsing")
+  }
+
+  pub fn algorithm(&self) -> Algorithm {
+    lookup(&algorithms(), "algorithm", &self.algorithm).expect("algorithm is validated while parsing")
+  }
+
+  // The orbits a density algorithm traces, None for the per-pixel algorithms
+  pub fn density(&self) -> Option<Density> {
+    match self.algorithm.as_str() {
+      BUDDHABROT => Some(Density::Buddhabrot(self.max_iterations)),
+      ANTI_BUDDHABROT => Some(Density::AntiBuddhabrot(self.max_iterations)),
+      NEBULABROT => Some(Density::Nebulabrot(self.nebulabrot_iterations)),
+      _ => None,
+    }
+  }
+
+  // Everything the algorithm can provide when saving, so a raw file can be recoloured any way
+  fn saved_channels(&self) -> Channels {
+    let provides = |algorithms: &[&str]| self.save_raw.is_some() && algorithms.contains(&self.algorithm.as_str());
+
+    Channels {
+      smooth: self.save_raw.is_some(),
+      distance: provides(&DERIVATIVE_ALGORITHMS),
+      period: provides(&PERIOD_ALGORITHMS),
+      multiplier: provides(&CYCLE_ALGORITHMS),
+      normal: provides(&DERIVATIVE_ALGORITHMS),
+      trap: provides(&TRAP_ALGORITHMS),
+      triangle: provides(&AVERAGE_ALGORITHMS),
+      stripe: provides(&AVERAGE_ALGORITHMS),
+      root: provides(&ROOT_ALGORITHMS),
+    }
+  }
+
+  pub fn parameters(&self) -> Parameters {
+    Parameters {
+      max_iterations: self.max_iterations,
+      bailout: self.bailout,
+      trap: self.trap,
+      stripe_density: self.stripe_density,
+      colouring: self.colouring,
+      interior: self.interior,
+      light: self.light,
+      palette: self.palette.clone(),
+      channels: self.saved_channels(),
+    }
+  }
+
+  // Arbitrary precision centre, and a viewport of f64 offsets from it
+  pub fn perturbation(&self) -> Result<((BigFixed, BigFixed), Viewport<f64>), String> {
+    let text_limbs = |texts: &[&String]| 2 + texts.iter().map(|text| text.len()).max().unwrap_or(0) * 4 / 64;
+
+    match &self.region {
+      Region::Default => {
+        let half_x = (DEFAULT_GRID_X.1 - DEFAULT_GRID_X.0) / 2.0;
+        let half_y = (DEFAULT_GRID_Y.1 - DEFAULT_GRID_Y.0) / 2.0;
+        let limbs = BigFixed::limbs_for_zoom(1.0, self.height);
+        let centre = (BigFixed::from_f64(DEFAULT_GRID_X.0 + half_x, limbs), BigFixed::from_f64(DEFAULT_GRID_Y.0 + half_y, limbs));
+
+        Ok((centre, Viewport::from_bounds((-half_x, half_x), (-half_y, half_y), self.width, self.height, self.aspect)))
+      },
+      Region::Centre { centre, zoom } => {
+        let limbs = BigFixed::limbs_for_zoom(*zoom, self.height).max(text_limbs(&[&centre.0, &centre.1]));
+        let centre = (parse_fixed("--centre", &centre.0, limbs)?, parse_fixed("--centre", &centre.1, limbs)?);
+
+        Ok((centre, Viewport::from_centre((0.0, 0.0), *zoom, self.width, self.height)))
+      },
+      Region::Bounds(bounds) => {
+        let limbs = text_limbs(&[&bounds[0], &bounds[1], &bounds[2], &bounds[3]]);
+        let [x0, x1, y0, y1] = [
+          parse_fixed("--bounds", &bounds[0], limbs)?,
+          parse_fixed("--bounds", &bounds[1], limbs)?,
+          parse_fixed("--bounds", &bounds[2], limbs)?,
+          parse_fixed("--bounds", &bounds[3], limbs)?,
+        ];
+
+        // Compared before rounding, the spans of deep bounds are far below the centre's integer limb
+        if !(x0 < x1 && y0 < y1) {
+          return Err(String::from("--bounds must be given as X0,X1,Y0,Y1 with X0 < X1 and Y0 < Y1"));
+        }
+
+        let half_x = x1.sub(&x0).half().to_f64();
+        let half_y = y1.sub(&y0).half().to_f64();
+
+        let centre = (x0.add(&x1).half(), y0.add(&y1).half());
+        Ok((centre, Viewport::from_bounds((-half_x, half_x), (-half_y, half_y), self.width, self.height, self.aspect)))
+      },
+    }
+  }
+
+  pub fn viewport<T: Real>(&self) -> Result<Viewport<T>, String> {
+    match &self.region {
+      Region::Default => {
+        let (default_x, default_y) = match (self.julia, &self.newton) {
+          (None, None) => (DEFAULT_GRID_X, DEFAULT_GRID_Y),
+          _ => (DEFAULT_JULIA_GRID_X, DEFAULT_JULIA_GRID_Y),
+        };
+        let grid_x = (T::from_f64(default_x.0), T::from_f64(default_x.1));
+        let grid_y = (T::from_f64(default_y.0), T::from_f64(default_y.1));
+
+        Ok(Viewport::from_bounds(grid_x, grid_y, self.width, self.height, self.aspect))
+      },
+      Region::Centre { centre, zoom } => {
+        let centre = (parse_real("--centre", &centre.0)?, parse_real("--centre", &centre.1)?);
+
+        Ok(Viewport::from_centre(centre, *zoom, self.width, self.height))
+      },
+      Region::Bounds(bounds) => {
+        let [x0, x1, y0, y1] = [
+          parse_real::<T>("--bounds", &bounds[0])?,
+          parse_real::<T>("--bounds", &bounds[1])?,
+          parse_real::<T>("--bounds", &bounds[2])?,
+          parse_real::<T>("--bounds", &bounds[3])?,
+        ];
+
+        if !(x0 < x1 && y0 < y1) {
+          return Err(String::from("--bounds must be given as X0,X1,Y0,Y1 with X0 < X1 and Y0 < Y1"));
+        }
+
+        Ok(Viewport::from_bounds((x0, x1), (y0, y1), self.width, self.height, self.aspect))
+      },
+    }
+  }
+}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+  let mut options = Options {
+    generator: String::from("parallel3"),
+    algorithm: String::from("bulb-period"),
+    precision: Precision::F32,
+    region: Region::Default,
+    aspect: Aspect::Stretch,
+    width: 6144,
+    height: 4096,
+    max_iterations: DEFAULT_MAX_ITERATIONS,
+    bailout: DEFAULT_BAILOUT,
+    julia: None,
+    formula: Fractal::Mandelbrot,
+    exponent: Exponent::Integer(2),
+    expression: None,
+    newton: None,
+    trap: Trap::Point((0.0, 0.0)),
+    stripe_density: DEFAULT_STRIPE_DENSITY,
+    colouring: Colouring::Banded,
+    interior: Interior::Black,
+    light: None,
+    palette: Palette::default(),
+    output: String::from("res/mandelbrot.png"),
+    save_raw: None,
+    load_raw: None,
+    series: true,
+    samples: DEFAULT_SAMPLES,
+    seed: None,
+    nebulabrot_iterations: DEFAULT_NEBULABROT_ITERATIONS,
+    benchmark: false,
+  };
+
+  let mut centre = None;
+  let mut zoom = None;
+  let mut bounds = None;
+  let mut palette_path = None;
+  let mut interpolation = None;
+  let mut palette_offset = None;
+  let mut palette_scale = None;
+  let mut palette_repeat = None;
+  let mut algorithm = None;
+  let mut max_iterations = None;
+  let mut colouring = None;
+  let mut julia = None;
+  let mut julia_pixel = None;
+  let mut trap_given = false;
+  let mut generator_given = false;
+  let mut stripe_density = None;
+  let mut lighting = false;
+  let mut light_angle = None;
+  let mut light_height = None;
+  let mut samples = None;
+  let mut seed = None;
+  let mut nebulabrot_iterations = None;
+
+  while let Some(arg) = args.next() {
+    let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-g" | "--generator" => {
+        options.generator = value(&arg)?;
+        generator_given = true;
+      },
+      "-a" | "--algorithm" => algorithm = Some(value(&arg)?),
+      "-p" | "--precision" => options.precision = match value(&arg)?.as_str() {
+        "f32" => Precision::F32,
+        "f64" => Precision::F64,
+        "dd" => Precision::DoubleDouble,
+        other => return Err(format!("unknown precision '{}', expected f32, f64 or dd", other)),
+      },
+      "-c" | "--centre" => {
+        let [x, y] = split_list(&arg, &value(&arg)?)?;
+        centre = Some((x, y));
+      },
+      "-z" | "--zoom" => zoom = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "-b" | "--bounds" => bounds = Some(split_list::<4>(&arg, &value(&arg)?)?),
+      "--aspect" => options.aspect = match value(&arg)?.as_str() {
+        "stretch" => Aspect::Stretch,
+        "fit" => Aspect::Fit,
+        "fill" => Aspect::Fill,
+        other => return Err(format!("unknown aspect '{}', expected stretch, fit or fill", other)),
+      },
+      "-i" | "--iterations" => max_iterations = Some(parse_number(&arg, &value(&arg)?)?),
+      "--bailout" => options.bailout = parse_number(&arg, &value(&arg)?)?,
+      "--julia" => {
+        let [x, y] = split_list(&arg, &value(&arg)?)?;
+        julia = Some((parse_real::<f64>(&arg, &x)?, parse_real::<f64>(&arg, &y)?));
+      },
+      "--julia-pixel" => {
+        let [px, py] = split_list(&arg, &value(&arg)?)?;
+        julia_pixel = Some((parse_number::<usize>(&arg, &px)?, parse_number::<usize>(&arg, &py)?));
+      },
+      "-f" | "--formula" => options.formula = lookup(&formulas(), "formula", &value(&arg)?)?,
+      "-d" | "--exponent" => options.exponent = parse_exponent(&arg, &value(&arg)?)?,
+      "-e" | "--expression" => {
+        let text = value(&arg)?;
+        let expression = Expression::parse(&text).map_err(|error| format!("invalid {} '{}': {}", arg, text, error))?;
+        options.expression = Some(expression);
+      },
+      "--newton-roots" | "--newton-coefficients" => {
+        if options.newton.is_some() {
+          return Err(String::from("--newton-roots and --newton-coefficients both give the polynomial, use only one"));
+        }
+
+        let text = value(&arg)?;
+        let numbers = parse_complex_list(&arg, &text)?;
+        let newton = match arg.as_str() {
+          "--newton-roots" => Newton::from_roots(&numbers),
+          _ => Newton::from_coefficients(&numbers),
+        };
+        options.newton = Some(newton.map_err(|error| format!("invalid {} '{}': {}", arg, text, error))?);
+      },
+      "--trap" => {
+        options.trap = parse_trap(&arg, &value(&arg)?)?;
+        trap_given = true;
+      },
+      "--colouring" => colouring = Some(match value(&arg)?.as_str() {
+        "banded" => Colouring::Banded,
+        "smooth" => Colouring::Smooth,
+        "histogram" => Colouring::Histogram,
+        "smooth-histogram" => Colouring::SmoothHistogram,
+        "distance" => Colouring::Distance,
+        "trap" => Colouring::Trap,
+        "tia" => Colouring::TriangleInequality,
+        "stripe" => Colouring::Stripe,
+        "root" => Colouring::Root,
+        other => return Err(format!("unknown colouring '{}', expected banded, smooth, histogram, smooth-histogram, distance, trap, tia, stripe or root", other)),
+      }),
+      "--stripe-density" => stripe_density = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "--interior" => options.interior = match value(&arg)?.as_str() {
+        "black" => Interior::Black,
+        "distance" => Interior::Distance,
+        "period" => Interior::Period,
+        "multiplier" => Interior::Multiplier,
+        "trap" => Interior::Trap,
+        other => return Err(format!("unknown interior '{}', expected black, distance, period, multiplier or trap", other)),
+      },
+      "--lighting" => lighting = true,
+      "--light-angle" => light_angle = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "--light-height" => light_height = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "--palette" => palette_path = Some(value(&arg)?),
+      "--interpolation" => interpolation = Some(match value(&arg)?.as_str() {
+        "rgb" => Interpolation::Rgb,
+        "oklab" => Interpolation::OkLab,
+        other => return Err(format!("unknown interpolation '{}', expected rgb or oklab", other)),
+      }),
+      "--palette-offset" => palette_offset = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "--palette-scale" => palette_scale = Some(parse_number::<f64>(&arg, &value(&arg)?)?),
+      "--palette-repeat" => palette_repeat = Some(match value(&arg)?.as_str() {
+        "clamp" => Repeat::Clamp,
+        "wrap" => Repeat::Wrap,
+        "mirror" => Repeat::Mirror,
+        other => return Err(format!("unknown palette repeat '{}', expected clamp, wrap or mirror", other)),
+      }),
+      "-W" | "--width" => options.width = parse_number(&arg, &value(&arg)?)?,
+      "-H" | "--height" => options.height = parse_number(&arg, &value(&arg)?)?,
+      "-o" | "--output" => options.output = value(&arg)?,
+      "--save-raw" => options.save_raw = Some(value(&arg)?),
+      "--load-raw" => options.load_raw = Some(value(&arg)?),
+      "--samples" => samples = Some(parse_number::<u32>(&arg, &value(&arg)?)?),
+      "--seed" => seed = Some(parse_number::<u64>(&arg, &value(&arg)?)?),
+      "--nebulabrot-iterations" => {
+        let [r, g, b] = split_list(&arg, &value(&arg)?)?;
+        nebulabrot_iterations = Some([parse_number::<u32>(&arg, &r)?, parse_number::<u32>(&arg, &g)?, parse_number::<u32>(&arg, &b)?]);
+      },
+      "--no-series" => options.series = false,
+      "--benchmark" => options.benchmark = true,
+      other => return Err(format!("unknown argument '{}'", other)),
+    }
+  }
+
+  // Newton's method has its own algorithm and colouring, either can still be chosen explicitly
+  let newton = options.newton.is_some();
+  options.algorithm = algorithm.unwrap_or_else(|| String::from(if newton { "newton" } else { "bulb-period" }));
+  let finds_roots = ROOT_ALGORITHMS.contains(&options.algorithm.as_str());
+  options.colouring = colouring.unwrap_or(if finds_roots { Colouring::Root } else { Colouring::Banded });
+
+  lookup(&generators::<f32, Mandelbrot, EscapeTimeKernel>(), "generator", &options.generator)?;
+  let density = DENSITY_ALGORITHMS.contains(&options.algorithm.as_str());
+  if options.algorithm != PERTURBATION && !density {
+    lookup(&algorithms(), "algorithm", &options.algorithm)?;
+  }
+  if options.algorithm == PERTURBATION && generator_given {
+    return Err(String::from("the perturbation algorithm schedules its own rows around the reference orbits, it cannot be combined with --generator"));
+  }
+
+  // Density renders trace every sample to the limit that never escapes, so they default to a lower one
+  options.max_iterations = max_iterations.unwrap_or(if density { DEFAULT_DENSITY_ITERATIONS } else { DEFAULT_MAX_ITERATIONS });
+  if options.max_iterations == 0 {
+    return Err(String::from("--iterations must be at least 1"));
+  }
+
+  if !density && (samples.is_some() || seed.is_some()) {
+    return Err(format!("--samples and --seed only apply to the density algorithms: {}", DENSITY_ALGORITHMS.join(", ")));
+  }
+  if options.algorithm != NEBULABROT && nebulabrot_iterations.is_some() {
+    return Err(String::from("--nebulabrot-iterations only applies to the nebulabrot algorithm"));
+  }
+  if density {
+    if options.algorithm == NEBULABROT && max_iterations.is_some() {
+      return Err(String::from("the nebulabrot has a limit per channel, set them with --nebulabrot-iterations instead of --iterations"));
+    }
+    if options.load_raw.is_some() || options.save_raw.is_some() {
+      return Err(format!("{} counts orbit points rather than iterating pixels, there is no raw iteration file to save or load", options.algorithm));
+    }
+    let colouring_given = colouring.is_some() || options.interior != Interior::Black || lighting || trap_given ||
+      stripe_density.is_some() || palette_path.is_some() || interpolation.is_some() || palette_offset.is_some() ||
+      palette_scale.is_some() || palette_repeat.is_some();
+    if colouring_given {
+      return Err(format!("{} tone-maps how often orbits visit each pixel, colouring, interior, lighting and palette options have no effect", options.algorithm));
+    }
+    if samples == Some(0) {
+      return Err(String::from("--samples must be at least 1"));
+    }
+    if nebulabrot_iterations.is_some_and(|limits| limits.contains(&0)) {
+      return Err(String::from("--nebulabrot-iterations must all be at least 1"));
+    }
+
+    options.samples = samples.unwrap_or(DEFAULT_SAMPLES);
+    options.seed = seed;
+    options.nebulabrot_iterations = nebulabrot_iterations.unwrap_or(DEFAULT_NEBULABROT_ITERATIONS);
+  }
+
+  if !(options.bailout >= DEFAULT_BAILOUT && options.bailout <= MAX_BAILOUT) {
+    return Err(format!("--bailout must be between {} and {}, got {}", DEFAULT_BAILOUT, MAX_BAILOUT, options.bailout));
+  }
+
+  if options.load_raw.is_some() && (options.save_raw.is_some() || options.benchmark) {
+    return Err(String::from("--load-raw only colours a saved render, it cannot be combined with --save-raw or --benchmark"));
+  }
+
+  if !lighting && (light_angle.is_some() || light_height.is_some()) {
+    return Err(String::from("light options have no effect without --lighting"));
+  }
+  if lighting {
+    let mut light = Light::default();
+    if let Some(angle) = light_angle {
+      light.angle = angle.to_radians();
+    }
+    if let Some(height) = light_height {
+      if !height.is_finite() || height < 0.0 {
+        return Err(format!("--light-height must be zero or more, got {}", height));
+      }
+      light.height = height;
+    }
+    options.light = Some(light);
+  }
+
+  let uses_trap = options.colouring == Colouring::Trap || options.interior == Interior::Trap;
+  if trap_given && !uses_trap {
+    return Err(String::from("--trap has no effect without --colouring trap or --interior trap"));
+  }
+
+  if let Some(density) = stripe_density {
+    if options.colouring != Colouring::Stripe {
+      return Err(String::from("--stripe-density has no effect without --colouring stripe"));
+    }
+    if options.load_raw.is_some() {
+      return Err(String::from("--stripe-density is fixed when rendering, it cannot be changed with --load-raw"));
+    }
+    if !density.is_finite() {
+      return Err(format!("invalid value '{}' for --stripe-density", density));
+    }
+    options.stripe_density = density;
+  }
+
+  if options.load_raw.is_none() {
+    let provided_by = |algorithms: &[&str]| algorithms.contains(&options.algorithm.as_str());
+
+    if lighting && !provided_by(&DERIVATIVE_ALGORITHMS) {
+      return Err(format!("lighting needs an algorithm that tracks the derivative: {}", DERIVATIVE_ALGORITHMS.join(", ")));
+    }
+
+    if options.colouring == Colouring::Distance && !provided_by(&DERIVATIVE_ALGORITHMS) {
+      return Err(format!("distance colouring needs an algorithm that tracks the derivative: {}", DERIVATIVE_ALGORITHMS.join(", ")));
+    }
+    if options.interior == Interior::Period && !provided_by(&PERIOD_ALGORITHMS) {
+      return Err(format!("period interiors need an algorithm that detects cycles: {}", PERIOD_ALGORITHMS.join(", ")));
+    }
+    if uses_trap && !provided_by(&TRAP_ALGORITHMS) {
+      return Err(format!("trap colouring needs an algorithm that measures orbits against the trap: {}", TRAP_ALGORITHMS.join(", ")));
+    }
+    if matches!(options.colouring, Colouring::TriangleInequality | Colouring::Stripe) && !provided_by(&AVERAGE_ALGORITHMS) {
+      return Err(format!("average colouring needs an algorithm that averages along orbits: {}", AVERAGE_ALGORITHMS.join(", ")));
+    }
+    if matches!(options.interior, Interior::Distance | Interior::Multiplier) && !provided_by(&CYCLE_ALGORITHMS) {
+      return Err(format!("distance and multiplier interiors need an algorithm that analyses cycles: {}", CYCLE_ALGORITHMS.join(", ")));
+    }
+    if options.colouring == Colouring::Root && !provided_by(&ROOT_ALGORITHMS) {
+      return Err(format!("root colouring needs an algorithm that finds roots: {}", ROOT_ALGORITHMS.join(", ")));
+    }
+  }
+
+  let palette_given = palette_path.is_some() || interpolation.is_some() ||
+    palette_offset.is_some() || palette_scale.is_some() || palette_repeat.is_some();
+  let uses_palette = !matches!(options.colouring, Colouring::Banded | Colouring::Distance) ||
+    matches!(options.interior, Interior::Period | Interior::Multiplier | Interior::Trap);
+  if palette_given && !uses_palette {
+    return Err(String::from("palette options have no effect on banded or distance colouring, choose another --colouring or --interior"));
+  }
+
+  if let Some(path) = palette_path {
+    options.palette = read_palette(&path).map_err(|error| format!("unable to load palette {}: {}", path, error))?;
+  }
+  if let Some(interpolation) = interpolation {
+    options.palette.interpolation = interpolation;
+  }
+  if let Some(offset) = palette_offset {
+    options.palette.offset = offset;
+  }
+  if let Some(scale) = palette_scale {
+    if !scale.is_finite() || scale <= 0.0 {
+      return Err(format!("--palette-scale must be positive, got {}", scale));
+    }
+    options.palette.scale = scale;
+  }
+  if let Some(repeat) = palette_repeat {
+    options.palette.repeat = repeat;
+  }
+
+  if options.width < 2 || options.height < 2 {
+    return Err(format!("image must be at least 2x2 pixels, got {}x{}", options.width, options.height));
+  }
+
+  options.region = match bounds {
+    Some(bounds) => {
+      if centre.is_some() || zoom.is_some() {
+        return Err(String::from("--bounds cannot be combined with --centre or --zoom"));
+      }
+
+      Region::Bounds(bounds)
+    },
+    None if centre.is_none() && zoom.is_none() => Region::Default,
+    None => {
+      let zoom = zoom.unwrap_or(1.0);
+      if !zoom.is_finite() || zoom <= 0.0 {
+        return Err(format!("--zoom must be positive, got {}", zoom));
+      }
+
+      let centre = centre.unwrap_or_else(|| match (julia, newton) {
+        (None, false) => (String::from("-0.5"), String::from("0")),
+        _ => (String::from("0"), String::from("0")),
+      });
+      Region::Centre { centre, zoom }
+    },
+  };
+
+  if let Some((px, py)) = julia_pixel {
+    if julia.is_some() {
+      return Err(String::from("--julia-pixel picks c itself, it cannot be combined with --julia"));
+    }
+    if px >= options.width || py >= options.height {
+      return Err(format!("--julia-pixel {},{} is outside the {}x{} image", px, py, options.width, options.height));
+    }
+
+    // The region options place the Mandelbrot set the pixel is picked from, its Julia set is shown whole
+    let mandelbrot = options.viewport::<f64>()?;
+    julia = Some(mandelbrot.point(px, py));
+    options.region = Region::Default;
+  }
+
+  if options.exponent != Exponent::Integer(2) {
+    if options.load_raw.is_some() {
+      return Err(String::from("--exponent only applies when rendering, it cannot be combined with --load-raw"));
+    }
+    if options.algorithm == PERTURBATION {
+      return Err(String::from("the perturbation algorithm only renders z^2 + c, choose another --algorithm for --exponent"));
+    }
+    if options.exponent.escape_radius() > MAX_BAILOUT {
+      return Err(format!("--exponent {} needs an escape radius past {}, use an exponent further from 1", options.exponent.value(), MAX_BAILOUT));
+    }
+  }
+
+  if options.formula != Fractal::Mandelbrot {
+    if options.load_raw.is_some() {
+      return Err(String::from("--formula only applies when rendering, it cannot be combined with --load-raw"));
+    }
+    if options.exponent != Exponent::Integer(2) {
+      return Err(String::from("--exponent only applies to the mandelbrot formula"));
+    }
+    if options.algorithm == PERTURBATION || DERIVATIVE_ALGORITHMS.contains(&options.algorithm.as_str()) {
+      return Err(format!("{} needs the analytic mandelbrot formula, choose another --algorithm for --formula", options.algorithm));
+    }
+  }
+
+  if options.expression.is_some() {
+    if options.load_raw.is_some() {
+      return Err(String::from("--expression only applies when rendering, it cannot be combined with --load-raw"));
+    }
+    if options.formula != Fractal::Mandelbrot || options.exponent != Exponent::Integer(2) {
+      return Err(String::from("--expression replaces the formula, it cannot be combined with --formula or --exponent"));
+    }
+    if options.algorithm == PERTURBATION || DERIVATIVE_ALGORITHMS.contains(&options.algorithm.as_str()) {
+      return Err(format!("{} needs a formula with a known derivative, choose another --algorithm for --expression", options.algorithm));
+    }
+  }
+
+  if newton {
+    if options.load_raw.is_some() {
+      return Err(String::from("--newton-roots and --newton-coefficients only apply when rendering, they cannot be combined with --load-raw"));
+    }
+    if options.formula != Fractal::Mandelbrot || options.exponent != Exponent::Integer(2) || options.expression.is_some() {
+      return Err(String::from("Newton's method replaces the formula, it cannot be combined with --formula, --exponent or --expression"));
+    }
+    if !ROOT_ALGORITHMS.contains(&options.algorithm.as_str()) {
+      return Err(format!("{} is an escape-time algorithm, Newton's method needs one that finds roots: {}", options.algorithm, ROOT_ALGORITHMS.join(", ")));
+    }
+    if options.bailout != DEFAULT_BAILOUT {
+      return Err(String::from("--bailout has no effect on Newton's method, its orbits settle on roots rather than escape"));
+    }
+    if matches!(options.colouring, Colouring::Smooth | Colouring::SmoothHistogram) {
+      return Err(String::from("smooth colourings need escaping orbits, choose banded, histogram or root for Newton's method"));
+    }
+    if julia.is_some() {
+      return Err(String::from("Newton's method has no Julia sets, --julia and --julia-pixel cannot be combined with it"));
+    }
+  } else if ROOT_ALGORITHMS.contains(&options.algorithm.as_str()) && options.load_raw.is_none() {
+    return Err(format!("the {} algorithm needs a polynomial, give --newton-roots or --newton-coefficients", options.algorithm));
+  }
+
+  if julia.is_some() {
+    if options.load_raw.is_some() {
+      return Err(String::from("--julia only applies when rendering, it cannot be combined with --load-raw"));
+    }
+    if options.algorithm == PERTURBATION {
+      return Err(String::from("the perturbation algorithm only renders the Mandelbrot set, choose another --algorithm for --julia"));
+    }
+    if options.interior == Interior::Distance {
+      return Err(String::from("interior distances are only estimated for the Mandelbrot set, choose another --interior for --julia"));
+    }
+  }
+  options.julia = julia;
+
+  // Catch malformed numbers before any rendering starts
+  if options.algorithm == PERTURBATION {
+    options.perturbation()?;
+  } else {
+    options.viewport::<f64>()?;
+  }
+
+  return Ok(Command::Render(Box::new(options)));
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], kind: &str, name: &str) -> Result<T, String> {
+  table.iter()
+    .find(|(entry, _)| *entry == name)
+    .map(|(_, value)| *value)
+    .ok_or_else(|| {
+      let names: Vec<&str> = table.iter().map(|(entry, _)| *entry).collect();
+      format!("unknown {} '{}', expected one of: {}", kind, name, names.join(", "))
+    })
+}
+
+fn parse_trap(arg: &str, value: &str) -> Result<Trap, String> {
+  let (shape, list) = value.split_once(':').ok_or(format!("expected SHAPE:NUMBERS for {}, e.g. point:0,0", arg))?;
+  let number = |text: &String| parse_number::<f64>(arg, text);
+
+  let trap = match shape {
+    "point" => {
+      let [x, y] = split_list(arg, list)?;
+      Trap::Point((number(&x)?, number(&y)?))
+    },
+    "line" => {
+      let [x, y, angle] = split_list(arg, list)?;
+      Trap::Line((number(&x)?, number(&y)?), number(&angle)?.to_radians())
+    },
+    "cross" => {
+      let [x, y] = split_list(arg, list)?;
+      Trap::Cross((number(&x)?, number(&y)?))
+    },
+    "circle" => {
+      let [x, y, radius] = split_list(arg, list)?;
+      Trap::Circle((number(&x)?, number(&y)?), number(&radius)?)
+    },
+    "stalks" => {
+      let [width] = split_list(arg, list)?;
+      let width = number(&width)?;
+      if !(width > 0.0 && width.is_finite()) {
+        return Err(format!("stalk width must be positive, got {}", width));
+      }
+      Trap::Stalks(width)
+    },
+    other => return Err(format!("unknown trap '{}', expected point, line, cross, circle or stalks", other)),
+  };
+
+  return Ok(trap);
+}
+
+// Whole exponents multiply out, the rest need the polar form. From -1 to 1 there is no escape-time fractal
+fn parse_exponent(arg: &str, value: &str) -> Result<Exponent, String> {
+  let exponent = parse_real::<f64>(arg, value)?;
+
+  if exponent >= 2.0 && exponent.fract() == 0.0 && exponent <= u32::MAX as f64 {
+    return Ok(Exponent::Integer(exponent as u32));
+  }
+  if exponent.abs() <= 1.0 {
+    return Err(format!("{} must be above 1 or below -1, got {}", arg, exponent));
+  }
+
+  return Ok(Exponent::Real(exponent));
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+  value.trim().parse().map_err(|_| format!("invalid value '{}' for {}", value, arg))
+}
+
+fn parse_real<T: Real>(arg: &str, value: &str) -> Result<T, String> {
+  let number: T = parse_number(arg, value)?;
+
+  if !number.to_f64().is_finite() {
+    return Err(format!("invalid value '{}' for {}", value, arg));
+  }
+
+  return Ok(number);
+}
+
+// Comma separated complex numbers, each written like a constant in an --expression
+fn parse_complex_list(arg: &str, value: &str) -> Result<Vec<(f64, f64)>, String> {
+  value.split(',')
+    .map(|part| match parse_constant(part) {
+      Ok((re, im)) if re.is_finite() && im.is_finite() => Ok((re, im)),
+      Ok(_) => Err(format!("invalid value '{}' for {}", part.trim(), arg)),
+      Err(error) => Err(format!("invalid value '{}' for {}: {}", part.trim(), arg, error)),
+    })
+    .collect()
+}
+
+fn parse_fixed(arg: &str, value: &str, limbs: usize) -> Result<BigFixed, String> {
+  BigFixed::parse(value, limbs).ok_or_else(|| format!("invalid value '{}' for {}", value, arg))
+}
+
+fn split_list<const N: usize>(arg: &str, value: &str) -> Result<[String; N], String> {
+  let parts: Vec<String> = value.split(',').map(|part| part.trim().to_string()).collect();
+
+  let count = parts.len();
+  parts.try_into().map_err(|_| format!("expected {} comma separated numbers for {}, got {}", N, arg, count))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Options {
+    match parse_args(args.iter().map(|arg| arg.to_string())) {
+      Ok(Command::Render(options)) => *options,
+      Ok(Command::Help) => panic!("expected options to render"),
+      Err(error) => panic!("{}", error),
+    }
+  }
+
+  // A box 1e-46 wide, far below the third limb of the centre
+  fn deep_bounds(reversed: bool) -> String {
+    let (x0, x1) = (format!("-1.25066{}1", "0".repeat(40)), String::from("-1.25066"));
+    let (y0, y1) = (String::from("0.02012"), format!("0.02012{}1", "0".repeat(40)));
+
+    match reversed {
+      false => format!("{},{},{},{}", x0, x1, y0, y1),
+      true => format!("{},{},{},{}", x1, x0, y0, y1),
+    }
+  }
+
+  #[test]
+  fn perturbation_accepts_bounds_narrower_than_an_f64_limb() {
+    let bounds = deep_bounds(false);
+    let options = parse(&["-a", "perturbation", "-W", "11", "-H", "11", "-b", &bounds]);
+    let (centre, viewport) = options.perturbation().expect("deep bounds are valid");
+
+    assert!((viewport.pixel_size() / 1e-47 - 1.0).abs() < 1e-6, "pixel size {}", viewport.pixel_size());
+    assert!((viewport.x0(10) - viewport.x0(0) - 1e-46).abs() < 1e-52);
+    assert!((centre.0.to_f64() + 1.25066).abs() < 1e-15);
+    assert!((centre.1.to_f64() - 0.02012).abs() < 1e-15);
+  }
+
+  #[test]
+  fn perturbation_rejects_reversed_deep_bounds() {
+    let bounds = deep_bounds(true);
+    let args = ["-a", "perturbation", "-b", &bounds];
+
+    assert!(parse_args(args.iter().map(|arg| arg.to_string())).is_err());
+  }
+
+  #[test]
+  fn perturbation_rejects_a_generator() {
+    let args = ["-a", "perturbation", "-g", "gms"];
+    let error = parse_args(args.iter().map(|arg| arg.to_string())).err().expect("perturbation has no generator");
+
+    assert!(error.contains("--generator"), "{}", error);
+  }
+}
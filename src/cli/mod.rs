@@ -0,0 +1,3 @@
+mod args;
+
+pub use args::*;
\ No newline at end of file
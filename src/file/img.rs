@@ -1,7 +1,21 @@
+extern crate flate2;
 extern crate png;
 
+use std::convert::TryInto;
 use std::fs::File;
 use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::mandelbrot::formula::Exponent;
+use crate::mandelbrot::iteration_buffer::IterationBuffer;
+use crate::mandelbrot::viewport::{Aspect, Region};
+
+const RAW_MAGIC: &[u8; 4] = b"MBIT";
+const RAW_VERSION: u8 = 2;
 
 #[derive(Debug)]
 pub struct Img {
@@ -11,27 +25,216 @@ pub struct Img {
     pub data: Vec<u8>,
 }
 
-pub fn read_png(path: &str) -> io::Result<Img> {
-    let decoder = png::Decoder::new(File::open(path)?);
-    let (info, mut reader) = decoder.read_info()?;
-    let mut img_data = vec![0; reader.output_buffer_size()];
+pub fn write_png(path: &str, img: Img) -> io::Result<()> {
+    let mut encoder = png::Encoder::new( File::create(path)?, img.width, img.height);
+    encoder.set_color(img.colour_type);
+    encoder.write_header()?.write_image_data(&img.data)?;
 
-    reader.next_frame(&mut img_data).expect("Unable to load png data");
+    return Ok(());
+}
 
-    return Ok(
-        Img {
-            colour_type: info.color_type,
-            width: info.width,
-            height: info.height,
-            data: img_data
-        }
-    );
+// A generator's escape data with what produced it, so an expensive render can be recoloured later
+#[derive(Debug)]
+pub struct RawIterations {
+    pub algorithm: String,
+    pub region: Region,
+    pub aspect: Aspect,
+    pub bailout: f64,
+    pub buffer: IterationBuffer,
 }
 
-pub fn write_png(path: &str, img: Img) -> io::Result<()> {
-    let mut encoder = png::Encoder::new( File::create(path)?, img.width, img.height);
-    encoder.set_color(img.colour_type);
-    encoder.write_header()?.write_image_data(&img.data).expect("Failed to write image");
+// Little endian throughout. The header is "MBIT", version u8, width u32, height u32, iteration limit u32,
+// bailout f64, aspect u8, the algorithm, a region tag u8 with its numbers, then flags u16 (1: smooth values,
+// 2: distances, 4: periods, 8: multipliers, 16: normals, 32: trap distances, 64: triangle inequality averages,
+// 128: stripe averages, 256: roots). Strings are a u16 length and UTF-8. A zlib stream follows with every pixel's
+// u32 iteration count, then each flagged channel in flag order: f32 smooth values and distances, u32 periods, f32
+// pairs of |λ| and arg λ for multipliers and of x and y for normals, f32 trap distances and averages, u32 roots.
+// Pixels are row-major from the top.
+pub fn write_raw(path: &str, raw: &RawIterations) -> io::Result<()> {
+    let buffer = &raw.buffer;
+    let mut file = BufWriter::new(File::create(path)?);
+
+    file.write_all(RAW_MAGIC)?;
+    file.write_all(&[RAW_VERSION])?;
+    write_u32(&mut file, buffer.width)?;
+    write_u32(&mut file, buffer.height)?;
+    file.write_all(&buffer.max_iterations.to_le_bytes())?;
+    file.write_all(&raw.bailout.to_le_bytes())?;
+    file.write_all(&[match raw.aspect {
+        Aspect::Stretch => 0,
+        Aspect::Fit => 1,
+        Aspect::Fill => 2,
+    }])?;
+    write_str(&mut file, &raw.algorithm)?;
+
+    match &raw.region {
+        Region::Default => file.write_all(&[0])?,
+        Region::Centre { centre, zoom } => {
+            file.write_all(&[1])?;
+            write_str(&mut file, &centre.0)?;
+            write_str(&mut file, &centre.1)?;
+            file.write_all(&zoom.to_le_bytes())?;
+        },
+        Region::Bounds(bounds) => {
+            file.write_all(&[2])?;
+            for bound in bounds {
+                write_str(&mut file, bound)?;
+            }
+        },
+    }
+
+    let channels = buffer.channels();
+    let flags = channels.smooth as u16 | (channels.distance as u16) << 1 | (channels.period as u16) << 2 |
+        (channels.multiplier as u16) << 3 | (channels.normal as u16) << 4 | (channels.trap as u16) << 5 |
+        (channels.triangle as u16) << 6 | (channels.stripe as u16) << 7 | (channels.root as u16) << 8;
+    file.write_all(&flags.to_le_bytes())?;
+
+    let mut data = ZlibEncoder::new(file, Compression::default());
+    for iterations in &buffer.iterations {
+        data.write_all(&iterations.to_le_bytes())?;
+    }
+    for channel in buffer.smooth.iter().chain(buffer.distance.iter()) {
+        for value in channel {
+            data.write_all(&value.to_le_bytes())?;
+        }
+    }
+    for period in buffer.period.iter().flatten() {
+        data.write_all(&period.to_le_bytes())?;
+    }
+    for (first, second) in buffer.multiplier.iter().chain(buffer.normal.iter()).flatten() {
+        data.write_all(&first.to_le_bytes())?;
+        data.write_all(&second.to_le_bytes())?;
+    }
+    for value in buffer.trap.iter().chain(buffer.triangle.iter()).chain(buffer.stripe.iter()).flatten() {
+        data.write_all(&value.to_le_bytes())?;
+    }
+    for root in buffer.root.iter().flatten() {
+        data.write_all(&root.to_le_bytes())?;
+    }
+    data.finish()?.flush()?;
 
     return Ok(());
-}
\ No newline at end of file
+}
+
+pub fn read_raw(path: &str) -> io::Result<RawIterations> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    if read_array::<4>(&mut file)? != *RAW_MAGIC {
+        return Err(invalid("not a raw iteration file"));
+    }
+
+    let version = read_array::<1>(&mut file)?[0];
+    if version != RAW_VERSION {
+        return Err(invalid(&format!("unsupported raw iteration file version {}", version)));
+    }
+
+    let width = u32::from_le_bytes(read_array(&mut file)?) as usize;
+    let height = u32::from_le_bytes(read_array(&mut file)?) as usize;
+    let max_iterations = u32::from_le_bytes(read_array(&mut file)?);
+    let bailout = f64::from_le_bytes(read_array(&mut file)?);
+
+    let aspect = match read_array::<1>(&mut file)?[0] {
+        0 => Aspect::Stretch,
+        1 => Aspect::Fit,
+        2 => Aspect::Fill,
+        other => return Err(invalid(&format!("unknown aspect {}", other))),
+    };
+
+    let algorithm = read_str(&mut file)?;
+
+    let region = match read_array::<1>(&mut file)?[0] {
+        0 => Region::Default,
+        1 => {
+            let centre = (read_str(&mut file)?, read_str(&mut file)?);
+            let zoom = f64::from_le_bytes(read_array(&mut file)?);
+            Region::Centre { centre, zoom }
+        },
+        2 => Region::Bounds([read_str(&mut file)?, read_str(&mut file)?, read_str(&mut file)?, read_str(&mut file)?]),
+        other => return Err(invalid(&format!("unknown region {}", other))),
+    };
+
+    let flags = u16::from_le_bytes(read_array(&mut file)?);
+    let pixels = width.checked_mul(height).ok_or_else(|| invalid("image dimensions overflow"))?;
+
+    let mut data = ZlibDecoder::new(file);
+    let iterations = read_values(&mut data, pixels)?.iter().map(|bytes| u32::from_le_bytes(*bytes)).collect();
+    let mut channel = |flag: u16, values_per_pixel: usize| -> io::Result<Option<Vec<[u8; 4]>>> {
+        if flags & flag == 0 {
+            return Ok(None);
+        }
+        Ok(Some(read_values(&mut data, values_per_pixel * pixels)?))
+    };
+    let floats = |values: Vec<[u8; 4]>| values.iter().map(|bytes| f32::from_le_bytes(*bytes)).collect::<Vec<f32>>();
+    let integers = |values: Vec<[u8; 4]>| values.iter().map(|bytes| u32::from_le_bytes(*bytes)).collect::<Vec<u32>>();
+
+    let smooth = channel(1, 1)?.map(floats);
+    let distance = channel(2, 1)?.map(floats);
+    let period = channel(4, 1)?.map(integers);
+    let pairs = |values: Vec<[u8; 4]>| floats(values).chunks_exact(2).map(|pair| (pair[0], pair[1])).collect::<Vec<(f32, f32)>>();
+    let multiplier = channel(8, 2)?.map(pairs);
+    let normal = channel(16, 2)?.map(pairs);
+    let trap = channel(32, 1)?.map(floats);
+    let triangle = channel(64, 1)?.map(floats);
+    let stripe = channel(128, 1)?.map(floats);
+    let root = channel(256, 1)?.map(integers);
+
+    // Distances are already in pixels and smooth values normalised, so the pixel size and exponent no longer matter
+    let buffer = IterationBuffer {
+        width,
+        height,
+        max_iterations,
+        pixel_size: 1.0,
+        exponent: Exponent::Integer(2),
+        iterations,
+        smooth,
+        distance,
+        period,
+        multiplier,
+        normal,
+        trap,
+        triangle,
+        stripe,
+        root,
+    };
+
+    return Ok(RawIterations { algorithm, region, aspect, bailout, buffer });
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u32(writer: &mut impl Write, value: usize) -> io::Result<()> {
+    let value: u32 = value.try_into().map_err(|_| invalid("image too large for a raw iteration file"))?;
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    let length: u16 = value.len().try_into().map_err(|_| invalid("string too long for a raw iteration file"))?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_str(reader: &mut impl Read) -> io::Result<String> {
+    let length = u16::from_le_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid("string is not UTF-8"))
+}
+
+// Read as far as the data goes rather than trusting the header's size for the allocation
+fn read_values(reader: &mut impl Read, count: usize) -> io::Result<Vec<[u8; 4]>> {
+    let mut bytes = vec!();
+    reader.take(4 * count as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != 4 * count {
+        return Err(invalid("pixel data is shorter than the image"));
+    }
+
+    Ok(bytes.chunks_exact(4).map(|chunk| chunk.try_into().expect("chunks are 4 bytes")).collect())
+}
@@ -1 +1,2 @@
-pub mod img;
\ No newline at end of file
+pub mod img;
+pub mod palette;
\ No newline at end of file
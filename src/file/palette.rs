@@ -0,0 +1,204 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mandelbrot::palette::{Palette, Repeat};
+
+// Pick the format from the extension: Fractint .map, GIMP .ggr or a .toml stop list
+pub fn read_palette(path: &str) -> io::Result<Palette> {
+    let text = fs::read_to_string(path)?;
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+
+    let palette = match extension.to_ascii_lowercase().as_str() {
+        "map" => parse_map(&text),
+        "ggr" => parse_ggr(&text),
+        "toml" => parse_toml(&text),
+        other => Err(format!("unknown palette format '.{}', expected .map, .ggr or .toml", other)),
+    };
+
+    return palette.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error));
+}
+
+// One "R G B" line per colour index, anything after the third number is a comment
+pub fn parse_map(text: &str) -> Result<Palette, String> {
+    let mut colours = vec!();
+
+    for (number, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().take(3).collect();
+        if fields.is_empty() {
+            continue;
+        }
+
+        let channel = |field: Option<&&str>| field
+            .and_then(|field| field.parse::<u8>().ok())
+            .ok_or(format!("line {}: expected three colour values from 0 to 255", number + 1));
+
+        colours.push((channel(fields.first())?, channel(fields.get(1))?, channel(fields.get(2))?));
+    }
+
+    // Fractint palettes are cyclic, one colour per iteration
+    let count = colours.len() as f64;
+    let stops: Vec<(f64, (u8, u8, u8))> = colours.into_iter()
+        .enumerate()
+        .map(|(index, colour)| (index as f64 / count, colour))
+        .collect();
+
+    let mut palette = Palette::new(&stops)?;
+    palette.scale = count;
+    palette.repeat = Repeat::Wrap;
+
+    return Ok(palette);
+}
+
+// Segments of "left middle right, left RGBA, right RGBA, blending, colouring". Every segment is
+// blended linearly in RGB, curved, sine, sphere and HSV segments come out close but not exact.
+pub fn parse_ggr(text: &str) -> Result<Palette, String> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    match lines.next() {
+        Some((_, line)) if line.trim() == "GIMP Gradient" => (),
+        _ => return Err(String::from("missing 'GIMP Gradient' header")),
+    }
+
+    let mut line = lines.next();
+    if let Some((_, name)) = line {
+        if name.starts_with("Name:") {
+            line = lines.next();
+        }
+    }
+
+    let count: usize = line
+        .and_then(|(_, line)| line.trim().parse().ok())
+        .ok_or("missing segment count")?;
+
+    let to_u8 = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut stops = vec!();
+
+    for _ in 0..count {
+        let (number, line) = lines.next().ok_or("fewer segments than the segment count")?;
+        let values: Vec<f64> = line.split_whitespace()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("line {}: malformed segment", number + 1))?;
+
+        if values.len() < 11 {
+            return Err(format!("line {}: a segment needs 11 values, got {}", number + 1, values.len()));
+        }
+
+        let (left, middle, right) = (values[0], values[1], values[2]);
+        let left_colour = (values[3], values[4], values[5]);
+        let right_colour = (values[7], values[8], values[9]);
+        let middle_colour = (
+            (left_colour.0 + right_colour.0) / 2.0,
+            (left_colour.1 + right_colour.1) / 2.0,
+            (left_colour.2 + right_colour.2) / 2.0,
+        );
+
+        for (position, (r, g, b)) in [(left, left_colour), (middle, middle_colour), (right, right_colour)] {
+            stops.push((position, (to_u8(r), to_u8(g), to_u8(b))));
+        }
+    }
+
+    let mut palette = Palette::new(&stops)?;
+    palette.repeat = Repeat::Mirror;
+
+    return Ok(palette);
+}
+
+#[derive(Default)]
+struct TomlStop {
+    position: Option<f64>,
+    colour: Option<(u8, u8, u8)>,
+}
+
+// A [[stop]] table per colour:
+//
+//   [[stop]]
+//   position = 0.5
+//   colour = "#ffaa00"
+pub fn parse_toml(text: &str) -> Result<Palette, String> {
+    let mut stops: Vec<TomlStop> = vec!();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        let error = |message: &str| format!("line {}: {}", number + 1, message);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line == "[[stop]]" {
+            stops.push(TomlStop::default());
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected [[stop]] or key = value"))?;
+        let stop = stops.last_mut().ok_or_else(|| error("key outside a [[stop]] table"))?;
+
+        match key.trim() {
+            "position" => stop.position = Some(value.trim().parse().map_err(|_| error("position must be a number"))?),
+            "colour" | "color" => stop.colour = Some(parse_hex(value.trim()).ok_or_else(|| error("colour must be a string like \"#rrggbb\""))?),
+            other => return Err(error(&format!("unknown key '{}'", other))),
+        }
+    }
+
+    let stops: Vec<(f64, (u8, u8, u8))> = stops.into_iter()
+        .enumerate()
+        .map(|(index, stop)| match (stop.position, stop.colour) {
+            (Some(position), Some(colour)) => Ok((position, colour)),
+            _ => Err(format!("stop {} needs both a position and a colour", index + 1)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    return Palette::new(&stops);
+}
+
+// The line up to a '#' outside any quoted string, a comment can follow a value
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut chars = line.char_indices();
+
+    while let Some((index, char)) = chars.next() {
+        match (quote, char) {
+            // Escapes only apply in basic strings, the escaped character can't close the string
+            (Some('"'), '\\') => {
+                chars.next();
+            },
+            (Some(open), _) if char == open => quote = None,
+            (None, '"' | '\'') => quote = Some(char),
+            (None, '#') => return &line[..index],
+            _ => {},
+        }
+    }
+
+    return line;
+}
+
+fn parse_hex(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('"')?.strip_suffix('"')?.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_allows_comments_after_values() {
+        let plain = parse_toml("[[stop]]\nposition = 0\ncolour = \"#000000\"\n[[stop]]\nposition = 0.5\ncolour = \"#ffaa00\"\n").unwrap();
+        let commented = parse_toml(
+            "# warm\n[[stop]]  # first\nposition = 0  # start\ncolour = \"#000000\"# black\n\
+             [[stop]]\nposition = 0.5  # midpoint\ncolour = \"#ffaa00\"  # orange # still the comment\n"
+        ).unwrap();
+
+        for t in [0.0, 0.25, 0.5, 0.75] {
+            assert_eq!(commented.colour_at(t), plain.colour_at(t));
+        }
+        assert!(parse_toml("[[stop]]\nposition = 0.5 # midpoint\ncolour = \"#ffaa00 # orange\"\n").is_err());
+    }
+}
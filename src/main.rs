@@ -1,41 +1,268 @@
+#![allow(clippy::needless_return, clippy::module_inception)]
+
 extern crate png;
 
+mod cli;
 mod file;
 mod utility;
 mod mandelbrot;
 
+use std::process::exit;
+
+use cli::*;
 use file::img::*;
-use mandelbrot::mandelbrot::*;
+use mandelbrot::buddhabrot::*;
+use mandelbrot::colour::colour_buffer;
+use mandelbrot::double_double::DoubleDouble;
 use mandelbrot::escape::*;
+use mandelbrot::formula::*;
+use mandelbrot::iteration_buffer::IterationBuffer;
+use mandelbrot::mandelbrot::gms_perturbation;
+use mandelbrot::perturbation::Perturbation;
+use mandelbrot::parameters::Parameters;
+use mandelbrot::real::Real;
+use mandelbrot::viewport::Viewport;
+use utility::benchmark;
 
 const FILE_SIZE_MB: usize = 1024 * 1024;
-const SCALE: u8 = 12;
-const ACCURACY: u8 = 15;
 
-const MAX_ITERATIONS: u32 = 1 << ACCURACY;
+fn main() {
+  let options = match parse_args(std::env::args().skip(1)) {
+    Ok(Command::Render(options)) => *options,
+    Ok(Command::Help) => {
+      print!("{}", USAGE);
+      return;
+    },
+    Err(error) => {
+      eprintln!("error: {}\n\nFor more information, try '--help'.", error);
+      exit(2);
+    },
+  };
 
-const SIZE: usize = 1 << SCALE;
-const SIZE_X: usize = ((SIZE as f32) * 1.5) as usize;
-const SIZE_Y: usize = SIZE;
-const COLOUR_ARRAY_SIZE: usize = SIZE_Y * SIZE_X * 3;
-const COLOUR_ROW_SIZE: usize = SIZE_X * 3;
-type ColourArrayRow = [u8; COLOUR_ROW_SIZE];
+  if let Some(path) = &options.load_raw {
+    recolour(&options, path);
+    return;
+  }
+
+  if options.algorithm == PERTURBATION {
+    render_perturbation(&options);
+    return;
+  }
+
+  match options.precision {
+    Precision::F32 => render::<f32>(&options),
+    Precision::F64 => render::<f64>(&options),
+    Precision::DoubleDouble => render::<DoubleDouble>(&options),
+  }
+}
+
+fn render<T: Real>(options: &Options) {
+  let bailout = options.bailout;
+
+  if let Some(newton) = options.newton.clone() {
+    let roots: Vec<String> = newton.roots().iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    println!("Newton's method on the polynomial with roots {}", roots.join(" "));
+    render_set::<T, _>(options, newton);
+    return;
+  }
+
+  if let Some(mut expression) = options.expression.clone() {
+    expression.bailout = bailout;
+    render_formula::<T, _>(options, expression);
+    return;
+  }
+
+  match options.formula {
+    Fractal::Mandelbrot if options.exponent == Exponent::Integer(2) => render_formula::<T, _>(options, Mandelbrot { bailout }),
+    Fractal::Mandelbrot => render_formula::<T, _>(options, Multibrot { exponent: options.exponent, bailout }),
+    Fractal::BurningShip => render_formula::<T, _>(options, BurningShip { bailout }),
+    Fractal::Tricorn => render_formula::<T, _>(options, Tricorn { bailout }),
+    Fractal::Celtic => render_formula::<T, _>(options, Celtic { bailout }),
+    Fractal::Buffalo => render_formula::<T, _>(options, Buffalo { bailout }),
+  }
+}
+
+fn render_formula<T: Real, F: Formula>(options: &Options, formula: F) {
+  match options.julia {
+    Some(c) => {
+      println!("Julia set of c = {},{}", c.0, c.1);
+      render_set::<T, _>(options, Julia { formula, c });
+    },
+    None => render_set::<T, _>(options, formula),
+  }
+}
+
+fn render_set<T: Real, F: Formula>(options: &Options, formula: F) {
+  if let Some(density) = options.density() {
+    render_density(options, formula, density);
+    return;
+  }
+
+  let viewport = match options.viewport::<T>() {
+    Ok(viewport) => viewport.with_symmetry(formula.is_mirrored(), formula.is_half_turn()),
+    Err(error) => {
+      eprintln!("error: {}", error);
+      exit(2);
+    },
+  };
+
+  // Matched once per render, every kernel gets generators of its own
+  match options.algorithm() {
+    Algorithm::EscapeTime => render_kernel(options, &viewport, &formula, EscapeTimeKernel),
+    Algorithm::Bulb => render_kernel(options, &viewport, &formula, BulbKernel),
+    Algorithm::Period => render_kernel(options, &viewport, &formula, PeriodKernel),
+    Algorithm::BulbPeriod => render_kernel(options, &viewport, &formula, BulbPeriodKernel),
+    Algorithm::Derivative => render_kernel(options, &viewport, &formula, DerivativeKernel),
+    Algorithm::Distance => render_kernel(options, &viewport, &formula, DistanceKernel),
+    Algorithm::Interior => render_kernel(options, &viewport, &formula, InteriorKernel),
+    Algorithm::Trap => render_kernel(options, &viewport, &formula, TrapKernel),
+    Algorithm::Average => render_kernel(options, &viewport, &formula, AverageKernel),
+    Algorithm::Newton => render_kernel(options, &viewport, &formula, NewtonKernel),
+  }
+}
+
+fn render_kernel<T: Real, F: Formula, K: Kernel>(options: &Options, viewport: &Viewport<T>, formula: &F, kernel: K) {
+  let generator = options.generator::<T, F, K>();
+
+  let params = options.parameters();
+  output(options, &params, || generator(viewport, &params, formula, kernel));
+}
+
+fn render_density<F: Formula>(options: &Options, formula: F, density: Density) {
+  let viewport = match options.viewport::<f64>() {
+    Ok(viewport) => viewport,
+    Err(error) => {
+      eprintln!("error: {}", error);
+      exit(2);
+    },
+  };
+  let samples = options.samples as u64 * viewport.width as u64 * viewport.height as u64;
+  let seed = options.seed.unwrap_or_else(rand::random);
+
+  if options.benchmark {
+    benchmark("density", || buddhabrot(&viewport, &formula, density, samples, seed));
+    return;
+  }
+
+  println!("Tracing {} orbits, seed {}", samples, seed);
+  let time_trace_orbits = std::time::SystemTime::now();
+  let buffer = buddhabrot(&viewport, &formula, density, samples, seed);
+  println!("{:?}, {} pixels", time_trace_orbits.elapsed(), buffer.width * buffer.height);
+
+  println!("Tone mapping");
+  let time_tone_map = std::time::SystemTime::now();
+  let data = tone_map(&buffer);
+  println!("{:?}, {}, {} MB", time_tone_map.elapsed(), data.len(), data.len() / FILE_SIZE_MB);
+
+  write_rgb(options, buffer.width, buffer.height, data);
+}
+
+fn render_perturbation(options: &Options) {
+  let (centre, viewport) = match options.perturbation() {
+    Ok(region) => region,
+    Err(error) => {
+      eprintln!("error: {}", error);
+      exit(2);
+    },
+  };
+
+  let perturbation = Perturbation { centre, series: options.series };
+
+  let params = options.parameters();
+  output(options, &params, || {
+    let (buffer, stats) = gms_perturbation(&viewport, &params, &perturbation);
+    if !options.benchmark {
+      println!(
+        "{} references, series skipped {:?} iterations, {} pixel iterations in total",
+        stats.series_skipped.len(), stats.series_skipped, stats.pixel_iterations_skipped
+      );
+    }
+    buffer
+  });
+}
+
+fn recolour(options: &Options, path: &str) {
+  println!("Reading raw iterations");
+  let time_read_set = std::time::SystemTime::now();
+  let raw = match read_raw(path) {
+    Ok(raw) => raw,
+    Err(error) => {
+      eprintln!("error: unable to read {}: {}", path, error);
+      exit(1);
+    },
+  };
+  println!("{:?}, {} of {:?}", time_read_set.elapsed(), raw.algorithm, raw.region);
+
+  let kept = raw.buffer.channels();
+  if options.colouring.channels().union(kept) != kept {
+    eprintln!("warning: {} lacks data for {:?} colouring, falling back on whole iterations", path, options.colouring);
+  }
+
+  let mut params = options.parameters();
+  params.max_iterations = raw.buffer.max_iterations;
+  params.bailout = raw.bailout;
+
+  write_image(options, &params, &raw.buffer);
+}
+
+fn output(options: &Options, params: &Parameters, generate: impl Fn() -> IterationBuffer) {
+  if options.benchmark {
+    benchmark("generator", &generate);
+    return;
+  }
 
-fn main() {
   println!("Generating Set");
   let time_generate_set = std::time::SystemTime::now();
-  let mandelbrot_set = gms_parallel3(escape_time_with_bulb_period);
-  println!("{:?}, {}, {} MB", time_generate_set.elapsed(), mandelbrot_set.len(), mandelbrot_set.len() / FILE_SIZE_MB);
+  let buffer = generate();
+  println!("{:?}, {} pixels", time_generate_set.elapsed(), buffer.len());
+
+  let buffer = match &options.save_raw {
+    Some(path) => {
+      let raw = RawIterations {
+        algorithm: options.algorithm.clone(),
+        region: options.region.clone(),
+        aspect: options.aspect,
+        bailout: params.bailout,
+        buffer,
+      };
+
+      let time_write_raw = std::time::SystemTime::now();
+      if let Err(error) = write_raw(path, &raw) {
+        eprintln!("error: unable to write {}: {}", path, error);
+        exit(1);
+      }
+      println!("Saved raw iterations. {:?}", time_write_raw.elapsed());
+
+      raw.buffer
+    },
+    None => buffer,
+  };
+
+  write_image(options, params, &buffer);
+}
+
+fn write_image(options: &Options, params: &Parameters, buffer: &IterationBuffer) {
+  println!("Colouring Set");
+  let time_colour_set = std::time::SystemTime::now();
+  let mandelbrot_set = colour_buffer(buffer, params);
+  println!("{:?}, {}, {} MB", time_colour_set.elapsed(), mandelbrot_set.len(), mandelbrot_set.len() / FILE_SIZE_MB);
+
+  write_rgb(options, buffer.width, buffer.height, mandelbrot_set);
+}
 
+fn write_rgb(options: &Options, width: usize, height: usize, data: Vec<u8>) {
   println!("About to write set to file");
   let new_png = Img {
-    colour_type: png::ColorType::RGB,
-    width: ((1  << SCALE) as f32 * 1.5) as u32,
-    height: 1 << SCALE,
-    data: mandelbrot_set
+    colour_type: png::ColorType::Rgb,
+    width: width as u32,
+    height: height as u32,
+    data
   };
 
   let time_write_set = std::time::SystemTime::now();
-  // write_png("res/mandelbrot.png", new_png).expect("Was unable to write png");
+  if let Err(error) = write_png(&options.output, new_png) {
+    eprintln!("error: unable to write {}: {}", options.output, error);
+    exit(1);
+  }
   println!("Finished# writing file. {:?}", time_write_set.elapsed());
 }
@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+
+// Signed fixed-point number: limbs[0] is the integer part and each following limb holds the next 64 fraction bits
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigFixed {
+  negative: bool,
+  limbs: Vec<u64>,
+}
+
+impl BigFixed {
+  pub fn zero(limbs: usize) -> BigFixed {
+    BigFixed { negative: false, limbs: vec![0; limbs.max(2)] }
+  }
+
+  // Enough fraction limbs to resolve a pixel at the zoom with a limb of guard bits left over
+  pub fn limbs_for_zoom(zoom: f64, height: usize) -> usize {
+    let bits = (zoom.max(1.0) * height as f64).log2().ceil() as usize;
+    2 + bits / 64 + 1
+  }
+
+  pub fn from_f64(value: f64, limbs: usize) -> BigFixed {
+    let mut result = BigFixed::zero(limbs);
+    let mut remaining = value.abs();
+
+    for limb in result.limbs.iter_mut() {
+      let whole = remaining.floor();
+      *limb = whole as u64;
+      remaining = (remaining - whole) * 18446744073709551616.0;
+      if remaining == 0.0 {
+        break;
+      }
+    }
+
+    result.negative = value < 0.0 && !result.is_zero();
+    return result;
+  }
+
+  pub fn parse(text: &str, limbs: usize) -> Option<BigFixed> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+      Some(index) => (&text[..index], text[index + 1..].parse::<i64>().ok()?),
+      None => (text, 0),
+    };
+
+    let (whole, fraction) = match mantissa.find('.') {
+      Some(index) => (&mantissa[..index], &mantissa[index + 1..]),
+      None => (mantissa, ""),
+    };
+
+    if whole.is_empty() && fraction.is_empty() || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+
+    // Move the decimal point by the exponent so the digits split into integer and fraction parts
+    let digits: Vec<u64> = whole.chars().chain(fraction.chars()).map(|c| c.to_digit(10).unwrap() as u64).collect();
+    let point = whole.len() as i64 + exponent;
+
+    let mut result = BigFixed::zero(limbs);
+
+    for (index, digit) in digits.iter().enumerate().rev() {
+      if (index as i64) < point {
+        break;
+      }
+      result.limbs[0] += digit;
+      result.div_small(10);
+    }
+    for _ in 0..(-point).max(0) {
+      result.div_small(10);
+    }
+
+    let mut integer: u64 = 0;
+    for (index, digit) in digits.iter().enumerate() {
+      if index as i64 >= point {
+        break;
+      }
+      integer = integer.checked_mul(10)?.checked_add(*digit)?;
+    }
+    for _ in 0..(point - digits.len() as i64).max(0) {
+      integer = integer.checked_mul(10)?;
+    }
+    result.limbs[0] = integer;
+
+    result.negative = negative && !result.is_zero();
+    return Some(result);
+  }
+
+  // Rounded from the first nonzero limb, so values far below the integer limb keep their precision
+  pub fn to_f64(&self) -> f64 {
+    let first = match self.limbs.iter().position(|limb| *limb != 0) {
+      Some(first) => first,
+      None => return 0.0,
+    };
+
+    let mut value = 0.0;
+    let mut weight = 1.0;
+
+    // Three limbs hold more than the 53 bits of an f64
+    for limb in self.limbs[first..].iter().take(3) {
+      value += *limb as f64 * weight;
+      weight /= 18446744073709551616.0;
+    }
+
+    let value = value * 2f64.powi(-64 * first as i32);
+    if self.negative { -value } else { value }
+  }
+
+  // Number of 64-bit limbs, including the integer limb
+  pub fn precision(&self) -> usize {
+    self.limbs.len()
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.limbs.iter().all(|limb| *limb == 0)
+  }
+
+  fn div_small(&mut self, divisor: u64) {
+    let mut remainder: u128 = 0;
+
+    for limb in self.limbs.iter_mut() {
+      let current = (remainder << 64) | *limb as u128;
+      *limb = (current / divisor as u128) as u64;
+      remainder = current % divisor as u128;
+    }
+  }
+
+  pub fn half(&self) -> BigFixed {
+    let mut result = self.clone();
+    result.div_small(2);
+    result.negative = self.negative && !result.is_zero();
+    return result;
+  }
+
+  pub fn compare(&self, other: &BigFixed) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, true) => Ordering::Greater,
+      (true, false) => Ordering::Less,
+      (false, false) => self.compare_magnitude(other),
+      (true, true) => other.compare_magnitude(self),
+    }
+  }
+
+  fn compare_magnitude(&self, other: &BigFixed) -> Ordering {
+    self.limbs.cmp(&other.limbs)
+  }
+
+  fn add_magnitude(&self, other: &BigFixed) -> Vec<u64> {
+    let mut limbs = vec![0; self.limbs.len()];
+    let mut carry = false;
+
+    for index in (0..limbs.len()).rev() {
+      let (sum, overflow_a) = self.limbs[index].overflowing_add(other.limbs[index]);
+      let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+      limbs[index] = sum;
+      carry = overflow_a || overflow_b;
+    }
+
+    return limbs;
+  }
+
+  // Assumes |self| >= |other|
+  fn sub_magnitude(&self, other: &BigFixed) -> Vec<u64> {
+    let mut limbs = vec![0; self.limbs.len()];
+    let mut borrow = false;
+
+    for index in (0..limbs.len()).rev() {
+      let (difference, overflow_a) = self.limbs[index].overflowing_sub(other.limbs[index]);
+      let (difference, overflow_b) = difference.overflowing_sub(borrow as u64);
+      limbs[index] = difference;
+      borrow = overflow_a || overflow_b;
+    }
+
+    return limbs;
+  }
+
+  pub fn add(&self, other: &BigFixed) -> BigFixed {
+    let result = if self.negative == other.negative {
+      BigFixed { negative: self.negative, limbs: self.add_magnitude(other) }
+    } else if self.compare_magnitude(other) != Ordering::Less {
+      BigFixed { negative: self.negative, limbs: self.sub_magnitude(other) }
+    } else {
+      BigFixed { negative: other.negative, limbs: other.sub_magnitude(self) }
+    };
+
+    BigFixed { negative: result.negative && !result.is_zero(), limbs: result.limbs }
+  }
+
+  pub fn sub(&self, other: &BigFixed) -> BigFixed {
+    self.add(&other.neg())
+  }
+
+  pub fn neg(&self) -> BigFixed {
+    BigFixed { negative: !self.negative && !self.is_zero(), limbs: self.limbs.clone() }
+  }
+
+  pub fn mul(&self, other: &BigFixed) -> BigFixed {
+    let count = self.limbs.len();
+    // Column sums of 64-bit halves, truncated below the last limb
+    let mut columns = vec![0u128; count];
+
+    for (i, a) in self.limbs.iter().enumerate() {
+      if *a == 0 {
+        continue;
+      }
+
+      for (j, b) in other.limbs.iter().enumerate().take(count + 1 - i) {
+        let product = *a as u128 * *b as u128;
+        let column = i + j;
+
+        if column < count {
+          columns[column] += product as u64 as u128;
+        }
+        if column > 0 {
+          columns[column - 1] += product >> 64;
+        }
+      }
+    }
+
+    let mut limbs = vec![0; count];
+    let mut carry: u128 = 0;
+    for index in (0..count).rev() {
+      let total = columns[index] + carry;
+      limbs[index] = total as u64;
+      carry = total >> 64;
+    }
+
+    let negative = self.negative != other.negative && limbs.iter().any(|limb| *limb != 0);
+    BigFixed { negative, limbs }
+  }
+
+  pub fn mul_small(&self, factor: u64) -> BigFixed {
+    let mut limbs = vec![0; self.limbs.len()];
+    let mut carry: u128 = 0;
+
+    for index in (0..limbs.len()).rev() {
+      let total = self.limbs[index] as u128 * factor as u128 + carry;
+      limbs[index] = total as u64;
+      carry = total >> 64;
+    }
+
+    BigFixed { negative: self.negative, limbs }
+  }
+}
+
+impl PartialOrd for BigFixed {
+  fn partial_cmp(&self, other: &BigFixed) -> Option<Ordering> {
+    Some(self.compare(other))
+  }
+}
@@ -0,0 +1,248 @@
+extern crate rand;
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::mandelbrot::formula::Formula;
+use crate::mandelbrot::viewport::Viewport;
+
+// Orbits are shorter than the escape time renders', every sample still runs to the limit where it never escapes
+pub const DEFAULT_DENSITY_ITERATIONS: u32 = 1 << 10;
+pub const DEFAULT_SAMPLES: u32 = 32;
+// Samples traced with one generator. Splitting the render by batch rather than by thread keeps the same seed
+// drawing the same c however many threads there are
+const BATCH_SAMPLES: u64 = 1 << 16;
+// Red, green and blue limits of the usual Nebulabrot
+pub const DEFAULT_NEBULABROT_ITERATIONS: [u32; 3] = [5000, 500, 50];
+
+// The sets all lie within |c| <= 2, so only c from there can have orbits that don't escape straight away
+const SAMPLE_RADIUS: f64 = 2.0;
+// Share of the pixels hit that tone mapping lets saturate, so a few hot spots don't darken the rest
+const TONE_CLIP: f64 = 1e-3;
+// Brings out the faint outer orbits against the dense core
+const TONE_GAMMA: f64 = 2.0;
+
+// Which orbits a density render traces, and how far
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Density {
+  // Orbits escaping within the limit
+  Buddhabrot(u32),
+  // Orbits still bound at the limit
+  AntiBuddhabrot(u32),
+  // A Buddhabrot per colour channel, red, green and blue, each to its own limit
+  Nebulabrot([u32; 3]),
+}
+
+impl Density {
+  fn channels(self) -> usize {
+    match self {
+      Density::Nebulabrot(limits) => limits.len(),
+      Density::Buddhabrot(_) | Density::AntiBuddhabrot(_) => 1,
+    }
+  }
+
+  fn max_iterations(self) -> u32 {
+    match self {
+      Density::Buddhabrot(limit) | Density::AntiBuddhabrot(limit) => limit,
+      Density::Nebulabrot(limits) => limits.iter().copied().max().unwrap_or(0),
+    }
+  }
+
+  // Whether an orbit escaping after the given iterations, or never, adds to the channel
+  fn counts(self, channel: usize, escaped: Option<u32>) -> bool {
+    match (self, escaped) {
+      (Density::Buddhabrot(_), escaped) => escaped.is_some(),
+      (Density::AntiBuddhabrot(_), escaped) => escaped.is_none(),
+      (Density::Nebulabrot(limits), Some(iterations)) => iterations <= limits[channel],
+      (Density::Nebulabrot(_), None) => false,
+    }
+  }
+}
+
+// Orbit points landing in each pixel, row-major from the top of the image, per channel
+#[derive(Clone, Debug)]
+pub struct DensityBuffer {
+  pub width: usize,
+  pub height: usize,
+  pub hits: Vec<Vec<u32>>,
+}
+
+// Traces the orbits of random c in batches the threads take in turn. Each batch has its own generator seeded from
+// the seed and the batch, so a render can be repeated whatever the number of threads. Where the formula is mirrored
+// conj(c) traces the conjugate orbit, so only the upper half plane is sampled and every point counts at its
+// reflection too
+pub fn buddhabrot<F: Formula>(viewport: &Viewport<f64>, formula: &F, density: Density, samples: u64, seed: u64) -> DensityBuffer {
+  let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+  return trace_batches(viewport, formula, density, samples, seed, threads);
+}
+
+fn trace_batches<F: Formula>(viewport: &Viewport<f64>, formula: &F, density: Density, samples: u64, seed: u64, threads: usize) -> DensityBuffer {
+  let pixels = viewport.width * viewport.height;
+  let hits: Vec<Vec<AtomicU32>> = (0..density.channels())
+    .map(|_| (0..pixels).map(|_| AtomicU32::new(0)).collect())
+    .collect();
+
+  let batches = samples.div_ceil(BATCH_SAMPLES);
+  let next_batch = AtomicU64::new(0);
+  let mirrored = formula.is_mirrored();
+  let lower = if mirrored { 0.0 } else { -SAMPLE_RADIUS };
+
+  thread::scope(|scope| {
+    for _ in 0..threads {
+      let (hits, next_batch) = (&hits, &next_batch);
+
+      scope.spawn(move || {
+        let mut orbit = Vec::with_capacity(density.max_iterations() as usize);
+        let add = |channel: &[AtomicU32], x: f64, y: f64| {
+          if let Some((px, py)) = viewport.pixel(x, y) {
+            channel[py * viewport.width + px].fetch_add(1, Ordering::Relaxed);
+          }
+        };
+
+        loop {
+          let batch = next_batch.fetch_add(1, Ordering::Relaxed);
+          if batch >= batches {
+            break;
+          }
+
+          let mut rng = StdRng::seed_from_u64(batch_seed(seed, batch));
+          for _ in 0..BATCH_SAMPLES.min(samples - batch * BATCH_SAMPLES) {
+            let x0 = rng.gen_range(-SAMPLE_RADIUS..SAMPLE_RADIUS);
+            let y0 = rng.gen_range(lower..SAMPLE_RADIUS);
+
+            // Points in a component the formula knows of never escape, and would run to the limit to show it
+            let escaped = match density {
+              Density::Buddhabrot(_) | Density::Nebulabrot(_) if formula.interior_period(y0, x0).is_some() => continue,
+              _ => trace(y0, x0, formula, density.max_iterations(), &mut orbit),
+            };
+
+            for (index, channel) in hits.iter().enumerate() {
+              if !density.counts(index, escaped) {
+                continue;
+              }
+
+              for &(x, y) in &orbit {
+                add(channel, x, y);
+                if mirrored {
+                  add(channel, x, -y);
+                }
+              }
+            }
+          }
+        }
+      });
+    }
+  });
+
+  DensityBuffer {
+    width: viewport.width,
+    height: viewport.height,
+    hits: hits.into_iter().map(|channel| channel.into_iter().map(AtomicU32::into_inner).collect()).collect(),
+  }
+}
+
+// Splitmix64 of the seed and batch mixed together. Adding the batch to the seed would hand seed N + 1 the batches of
+// seed N one along, so neighbouring seeds would trace nearly the same samples
+fn batch_seed(seed: u64, batch: u64) -> u64 {
+  let mut z = seed ^ batch.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+  return z ^ (z >> 31);
+}
+
+// Iterates c to the limit, keeping every z after the first. Returns the iteration it escaped at, None if it never did
+fn trace<F: Formula>(y0: f64, x0: f64, formula: &F, max_iterations: u32, orbit: &mut Vec<(f64, f64)>) -> Option<u32> {
+  let bailout = formula.bailout_squared();
+  orbit.clear();
+
+  let (c, (mut x, mut y)) = formula.start(y0, x0);
+  let mut x2 = x * x;
+  let mut y2 = y * y;
+  for iterations in 1..=max_iterations {
+    (x, y) = formula.step(x, y, x2, y2, c);
+    x2 = x * x;
+    y2 = y * y;
+
+    orbit.push((x, y));
+    if x2 + y2 > bailout {
+      return Some(iterations);
+    }
+  }
+
+  return None;
+}
+
+// RGB bytes, a single channel in grey. Each channel is scaled to its own level so the red, green and blue of a
+// Nebulabrot all reach full brightness however many fewer points the shorter limits trace
+pub fn tone_map(buffer: &DensityBuffer) -> Vec<u8> {
+  let levels: Vec<f64> = buffer.hits.iter().map(|channel| level(channel)).collect();
+  let mut rgb = vec![0; 3 * buffer.width * buffer.height];
+
+  for (index, pixel) in rgb.chunks_mut(3).enumerate() {
+    for (colour, value) in pixel.iter_mut().enumerate() {
+      let channel = colour.min(buffer.hits.len() - 1);
+      let brightness = (buffer.hits[channel][index] as f64 / levels[channel]).min(1.0);
+
+      *value = (255.0 * brightness.powf(1.0 / TONE_GAMMA)) as u8;
+    }
+  }
+
+  return rgb;
+}
+
+// Hits counted as full brightness, all but the TONE_CLIP share of the pixels hit fall below it
+fn level(hits: &[u32]) -> f64 {
+  let mut hit: Vec<u32> = hits.iter().copied().filter(|hits| *hits > 0).collect();
+  if hit.is_empty() {
+    return 1.0;
+  }
+
+  let index = ((hit.len() as f64 * (1.0 - TONE_CLIP)) as usize).min(hit.len() - 1);
+  let (_, level, _) = hit.select_nth_unstable(index);
+
+  return *level as f64;
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use super::*;
+  use crate::mandelbrot::formula::Mandelbrot;
+
+  #[test]
+  fn seed_renders_the_same_on_any_number_of_threads() {
+    let viewport = Viewport::from_centre((-0.5, 0.0), 0.5, 40, 30);
+    let formula = Mandelbrot { bailout: 2.0 };
+    let density = Density::Buddhabrot(200);
+    // Not a whole number of batches, so the last is cut short
+    let samples = BATCH_SAMPLES * 2 + 1000;
+
+    let one = trace_batches(&viewport, &formula, density, samples, 7, 1);
+    let three = trace_batches(&viewport, &formula, density, samples, 7, 3);
+    assert_eq!(one.hits, three.hits);
+    assert!(one.hits[0].iter().any(|&hits| hits > 0));
+  }
+
+  #[test]
+  fn neighbouring_seeds_trace_different_samples() {
+    let viewport = Viewport::from_centre((-0.5, 0.0), 0.5, 40, 30);
+    let formula = Mandelbrot { bailout: 2.0 };
+    let density = Density::Buddhabrot(200);
+    let samples = BATCH_SAMPLES * 2;
+
+    let seven = trace_batches(&viewport, &formula, density, samples, 7, 1);
+    let eight = trace_batches(&viewport, &formula, density, samples, 8, 1);
+    assert_ne!(seven.hits, eight.hits);
+  }
+
+  #[test]
+  fn no_two_batches_share_a_generator() {
+    let seeds: HashSet<u64> = (0..64).flat_map(|seed| (0..64).map(move |batch| batch_seed(seed, batch))).collect();
+    assert_eq!(seeds.len(), 64 * 64);
+  }
+}
@@ -1,15 +1,161 @@
-use crate::{ColourArrayRow, MAX_ITERATIONS, SIZE_X, SIZE_Y};
+use std::f64::consts::TAU;
+use std::thread;
+
+use crate::mandelbrot::escape::{EscapeResult, Trap};
+use crate::mandelbrot::formula::Exponent;
+use crate::mandelbrot::iteration_buffer::{Channels, IterationBuffer};
+use crate::mandelbrot::parameters::Parameters;
+use crate::mandelbrot::real::Real;
 
 pub const COLOUR_SCALE: u32 = 4;
 pub const COLOUR_BRIGHTNESS: u32 = 0xff >> COLOUR_SCALE;
 pub const COLOUR_DEPTH: u32 = 0xff >> (8 - COLOUR_SCALE);
 
-pub const COLOUR_R: u32 = COLOUR_DEPTH << (COLOUR_SCALE * 2);
-pub const COLOUR_G: u32 = COLOUR_DEPTH << (COLOUR_SCALE * 1);
-pub const COLOUR_B: u32 = COLOUR_DEPTH << (COLOUR_SCALE * 0);
+// Distance in pixels at which distance colouring fades to white
+pub const DISTANCE_FADE: f64 = 2.0;
+
+// Share of a lit colour that stays in full shadow
+const LIGHT_AMBIENT: f64 = 0.2;
+
+// Spreads consecutive periods far apart along the palette
+const GOLDEN_RATIO_FRACTION: f64 = 0.618_033_988_749_895;
+
+// Iterations it takes root colouring to darken by half
+const ROOT_HALF_LIFE: f64 = 16.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colouring {
+  // Integer iteration counts wrapped onto a repeating cycle of colour bands
+  Banded,
+  // Normalised iteration count looked up in the palette
+  Smooth,
+  // Palette position from the share of pixels escaping sooner
+  Histogram,
+  // As Histogram, blending between neighbouring counts by the normalised iteration count
+  SmoothHistogram,
+  // Dark lines along the boundary from the exterior distance estimate, white elsewhere
+  Distance,
+  // Palette position from how close the orbit comes to the orbit trap
+  Trap,
+  // Palette position from the triangle inequality average of the orbit
+  TriangleInequality,
+  // Palette position from the average of sin(density arg z) along the orbit
+  Stripe,
+  // Roots a Newton orbit converges to spread evenly along the palette, darker the longer it took
+  Root,
+}
+
+impl Colouring {
+  // What the generators need to keep for this colouring
+  pub fn channels(self) -> Channels {
+    Channels {
+      smooth: matches!(self, Colouring::Smooth | Colouring::SmoothHistogram),
+      distance: self == Colouring::Distance,
+      trap: self == Colouring::Trap,
+      triangle: self == Colouring::TriangleInequality,
+      stripe: self == Colouring::Stripe,
+      root: self == Colouring::Root,
+      ..Channels::default()
+    }
+  }
+}
+
+// A distant light shining on the exterior as if it were a surface, embossing the palette colours
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+  // Direction the light comes from across the image, radians anticlockwise from the positive real axis
+  pub angle: f64,
+  // Height of the light above the image plane, higher flattens the relief
+  pub height: f64,
+}
+
+impl Light {
+  // Lambertian reflection off the surface with the given unit normal, 0 to 1
+  pub fn reflection(&self, normal: (f32, f32)) -> f64 {
+    let (x, y) = (normal.0 as f64, normal.1 as f64);
+    let facing = x * self.angle.cos() + y * self.angle.sin() + self.height;
+
+    (facing / (1.0 + self.height)).max(0.0)
+  }
+}
+
+impl Default for Light {
+  fn default() -> Light {
+    Light { angle: 45f64.to_radians(), height: 1.5 }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interior {
+  Black,
+  // Shaded by the interior distance estimate, dark against the boundary
+  Distance,
+  // A palette colour per cycle length
+  Period,
+  // Palette position from arg λ of the attracting cycle, darkening as |λ| nears 1 at the boundary
+  Multiplier,
+  // As the Trap colouring
+  Trap,
+}
+
+impl Interior {
+  pub fn channels(self) -> Channels {
+    Channels {
+      distance: self == Interior::Distance,
+      period: self == Interior::Period,
+      multiplier: self == Interior::Multiplier,
+      trap: self == Interior::Trap,
+      ..Channels::default()
+    }
+  }
+}
+
+// Cumulative distribution of escape iterations over a rendered set
+pub struct Histogram {
+  // Distinct iteration counts, ascending, with the fraction of escaped pixels at or below each
+  cumulative: Vec<(u32, f64)>,
+}
+
+impl Histogram {
+  pub fn new(buffer: &IterationBuffer) -> Histogram {
+    let mut iterations: Vec<u32> = buffer.iterations.iter()
+      .copied()
+      .filter(|iterations| *iterations < buffer.max_iterations)
+      .collect();
+    iterations.sort_unstable();
 
-pub fn colour(iterations: u32) -> (u8, u8, u8) {
-  let colour = if MAX_ITERATIONS == iterations { 0 } else { 1 + (iterations - 1) % (COLOUR_DEPTH * 3) };
+    let total = iterations.len() as f64;
+    let mut cumulative: Vec<(u32, f64)> = vec!();
+
+    for (index, count) in iterations.iter().enumerate() {
+      if iterations.get(index + 1) != Some(count) {
+        cumulative.push((*count, (index + 1) as f64 / total));
+      }
+    }
+
+    Histogram { cumulative }
+  }
+
+  // Fraction of escaped pixels at or below the count
+  fn fraction(&self, iterations: f64) -> f64 {
+    match self.cumulative.partition_point(|(count, _)| *count as f64 <= iterations) {
+      0 => 0.0,
+      index => self.cumulative[index - 1].1,
+    }
+  }
+
+  // Palette position for an iteration count, fractional counts blend between their neighbours
+  pub fn position(&self, value: f64) -> f64 {
+    let below = value.floor();
+    let low = self.fraction(below);
+    let high = self.fraction(below + 1.0);
+
+    low + (high - low) * (value - below)
+  }
+}
+
+pub fn colour_banded(iterations: u32, max_iterations: u32) -> (u8, u8, u8) {
+  let colour = if max_iterations == iterations { 0 } else { 1 + (iterations - 1) % (COLOUR_DEPTH * 3) };
 
   (
     (COLOUR_BRIGHTNESS * (colour / 3)) as u8,
@@ -18,37 +164,123 @@ pub fn colour(iterations: u32) -> (u8, u8, u8) {
   )
 }
 
-pub fn colour_row(set_colour: &mut [u8], px: usize, iterations: u32) -> () {
-  let p = 3 * px;
+// mu = n + 1 - log_d(ln|z|), continuous across iteration bands for any bailout radius
+pub fn smooth_iterations<T: Real>(result: &EscapeResult<T>, exponent: Exponent) -> f64 {
+  let log_modulus = 0.5 * result.magnitude.to_f64().ln();
+  (result.iterations as f64 + 1.0 - exponent.log(log_modulus)).max(0.0)
+}
+
+// Filaments thinner than a pixel still come out as a continuous grey line
+pub fn colour_distance(pixels: f64) -> (u8, u8, u8) {
+  let shade = (255.0 * (pixels / DISTANCE_FADE).clamp(0.0, 1.0).sqrt()) as u8;
+  (shade, shade, shade)
+}
+
+// Colour one pixel of the buffer, histogram colourings need the histogram of the whole buffer and root colouring
+// the number of roots in it
+pub fn colour(buffer: &IterationBuffer, index: usize, histogram: Option<&Histogram>, roots: u32, params: &Parameters) -> (u8, u8, u8) {
+  let iterations = buffer.iterations[index];
+  if !buffer.is_escaped(index) {
+    return colour_interior(buffer, index, params);
+  }
 
-  let (r, g, b) = colour(iterations);
+  if let (Colouring::Root, Some(root)) = (params.colouring, &buffer.root) {
+    return colour_root(root[index], roots, iterations, params);
+  }
 
-  set_colour[p] = r;
-  set_colour[p + 1] = g;
-  set_colour[p + 2] = b;
+  // Buffers without normalised counts fall back on the integer ones, without distances on banding
+  let smooth = || buffer.smooth.as_ref().map_or(iterations as f64, |smooth| smooth[index] as f64);
+
+  let average = match params.colouring {
+    Colouring::TriangleInequality => &buffer.triangle,
+    _ => &buffer.stripe,
+  };
+
+  let colour = match (params.colouring, histogram, &buffer.distance, &buffer.trap, average) {
+    (Colouring::Smooth, _, _, _, _) => params.palette.colour(smooth()),
+    (Colouring::Histogram, Some(histogram), _, _, _) => params.palette.colour_at(histogram.position(iterations as f64)),
+    (Colouring::SmoothHistogram, Some(histogram), _, _, _) => params.palette.colour_at(histogram.position(smooth())),
+    (Colouring::Distance, _, Some(distance), _, _) => colour_distance(distance[index] as f64),
+    (Colouring::Trap, _, _, Some(trap), _) => colour_trap(trap[index] as f64, smooth(), params),
+    (Colouring::TriangleInequality | Colouring::Stripe, _, _, _, Some(average)) => params.palette.colour_at(average[index] as f64),
+    _ => colour_banded(iterations, buffer.max_iterations),
+  };
+
+  match (&params.light, &buffer.normal) {
+    (Some(light), Some(normal)) => shade(colour, LIGHT_AMBIENT + (1.0 - LIGHT_AMBIENT) * light.reflection(normal[index])),
+    _ => colour,
+  }
+}
+
+fn shade((r, g, b): (u8, u8, u8), brightness: f64) -> (u8, u8, u8) {
+  ((r as f64 * brightness) as u8, (g as f64 * brightness) as u8, (b as f64 * brightness) as u8)
 }
 
-pub fn colour_set(set_colour: &mut [u8], px: usize, py: usize, iterations: u32) -> () {
-  let p = 3 * (py * SIZE_X + px);
+// One pass through the palette per unit of distance. Stalks only darken the smooth colouring where orbits pass
+// within their width
+pub fn colour_trap(distance: f64, smooth: f64, params: &Parameters) -> (u8, u8, u8) {
+  match params.trap {
+    Trap::Stalks(width) if distance < width => shade(params.palette.colour(smooth), distance / width),
+    Trap::Stalks(_) => params.palette.colour(smooth),
+    _ => params.palette.colour_at(distance),
+  }
+}
+
+// Roots are few, so unlike periods they can share the palette out evenly
+pub fn colour_root(root: u32, roots: u32, iterations: u32, params: &Parameters) -> (u8, u8, u8) {
+  let colour = params.palette.colour_at(root.saturating_sub(1) as f64 / roots.max(1) as f64);
+  shade(colour, 0.5f64.powf(iterations as f64 / ROOT_HALF_LIFE))
+}
 
-  let (r, g, b) = colour(iterations);
+// Interior points without the data for the interior mode, e.g. no cycle found in time, stay black
+pub fn colour_interior(buffer: &IterationBuffer, index: usize, params: &Parameters) -> (u8, u8, u8) {
+  if let (Interior::Trap, Some(trap)) = (params.interior, &buffer.trap) {
+    return colour_trap(trap[index] as f64, 0.0, params);
+  }
 
-  set_colour[p] = r;
-  set_colour[p + 1] = g;
-  set_colour[p + 2] = b;
+  match (params.interior, &buffer.distance, &buffer.period, &buffer.multiplier) {
+    (Interior::Distance, Some(distance), _, _) if distance[index] > 0.0 => colour_distance(distance[index] as f64),
+    (Interior::Period, _, Some(period), _) if period[index] > 0 => {
+      params.palette.colour_at((period[index] as f64 * GOLDEN_RATIO_FRACTION).fract())
+    },
+    (Interior::Multiplier, _, _, Some(multiplier)) if multiplier[index] != (0.0, 0.0) => {
+      let (modulus, argument) = multiplier[index];
+      let colour = params.palette.colour_at(argument as f64 / TAU + 0.5);
+      shade(colour, (1.0 - modulus as f64).clamp(0.0, 1.0).sqrt())
+    },
+    _ => (0, 0, 0),
+  }
 }
 
-pub fn colour_half(set_colour: &mut Vec<u8>, px: usize, py: usize, iterations: u32) -> () {
-  let p = 3 * (py * SIZE_X + px);
-  let rp = 3 * ((SIZE_Y - py - 1) * SIZE_X + px);
+// The colouring pass, turns a generator's buffer into RGB bytes
+pub fn colour_buffer(buffer: &IterationBuffer, params: &Parameters) -> Vec<u8> {
+  let histogram = match params.colouring {
+    Colouring::Histogram | Colouring::SmoothHistogram => Some(Histogram::new(buffer)),
+    Colouring::Banded | Colouring::Smooth | Colouring::Distance | Colouring::Trap |
+      Colouring::TriangleInequality | Colouring::Stripe | Colouring::Root => None,
+  };
+  let histogram = histogram.as_ref();
+  let roots = buffer.root.as_ref().and_then(|root| root.iter().max().copied()).unwrap_or(0);
+
+  let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+  let chunk_size = buffer.len().div_ceil(threads).max(1);
+  let mut set_colour = vec![0; 3 * buffer.len()];
+
+  thread::scope(|scope| {
+    for (chunk, colours) in set_colour.chunks_mut(3 * chunk_size).enumerate() {
+      scope.spawn(move || {
+        let offset = chunk * chunk_size;
 
-  let (r, g, b) = colour(iterations);
+        for (index, pixel) in colours.chunks_mut(3).enumerate() {
+          let (r, g, b) = colour(buffer, offset + index, histogram, roots, params);
 
-  set_colour[p] = r;
-  set_colour[p + 1] = g;
-  set_colour[p + 2] = b;
+          pixel[0] = r;
+          pixel[1] = g;
+          pixel[2] = b;
+        }
+      });
+    }
+  });
 
-  set_colour[rp] = r;
-  set_colour[rp + 1] = g;
-  set_colour[rp + 2] = b;
+  return set_colour;
 }
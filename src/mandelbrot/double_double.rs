@@ -0,0 +1,196 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use crate::mandelbrot::real::Real;
+
+// Unevaluated sum of two f64s giving ~32 significant digits, enough for zooms to roughly 1e-30
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+  hi: f64,
+  lo: f64,
+}
+
+// 2^27 + 1, splits an f64 mantissa into two halves that multiply exactly
+const SPLITTER: f64 = 134217729.0;
+
+#[inline(always)]
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+  let s = a + b;
+  (s, b - (s - a))
+}
+
+#[inline(always)]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+  let s = a + b;
+  let bb = s - a;
+  (s, (a - (s - bb)) + (b - bb))
+}
+
+#[inline(always)]
+fn split(a: f64) -> (f64, f64) {
+  let t = SPLITTER * a;
+  let hi = t - (t - a);
+  (hi, a - hi)
+}
+
+#[inline(always)]
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+  let p = a * b;
+  let (a_hi, a_lo) = split(a);
+  let (b_hi, b_lo) = split(b);
+  (p, ((a_hi * b_hi - p) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo)
+}
+
+impl DoubleDouble {
+  pub const fn new(hi: f64, lo: f64) -> DoubleDouble {
+    DoubleDouble { hi, lo }
+  }
+
+  fn powi10(exponent: i32) -> DoubleDouble {
+    let mut result = DoubleDouble::new(1.0, 0.0);
+    let mut base = DoubleDouble::new(10.0, 0.0);
+    let mut remaining = exponent.unsigned_abs();
+
+    while remaining > 0 {
+      if remaining & 1 == 1 {
+        result = result * base;
+      }
+      base = base * base;
+      remaining >>= 1;
+    }
+
+    if exponent < 0 { DoubleDouble::new(1.0, 0.0) / result } else { result }
+  }
+}
+
+impl Add for DoubleDouble {
+  type Output = DoubleDouble;
+
+  #[inline(always)]
+  fn add(self, other: DoubleDouble) -> DoubleDouble {
+    let (s, e) = two_sum(self.hi, other.hi);
+    let (t, f) = two_sum(self.lo, other.lo);
+    let (s, e) = quick_two_sum(s, e + t);
+    let (hi, lo) = quick_two_sum(s, e + f);
+    DoubleDouble { hi, lo }
+  }
+}
+
+impl Sub for DoubleDouble {
+  type Output = DoubleDouble;
+
+  #[inline(always)]
+  fn sub(self, other: DoubleDouble) -> DoubleDouble {
+    self + -other
+  }
+}
+
+impl Mul for DoubleDouble {
+  type Output = DoubleDouble;
+
+  #[inline(always)]
+  fn mul(self, other: DoubleDouble) -> DoubleDouble {
+    let (p, e) = two_prod(self.hi, other.hi);
+    let (hi, lo) = quick_two_sum(p, e + (self.hi * other.lo + self.lo * other.hi));
+    DoubleDouble { hi, lo }
+  }
+}
+
+impl Div for DoubleDouble {
+  type Output = DoubleDouble;
+
+  fn div(self, other: DoubleDouble) -> DoubleDouble {
+    let q1 = self.hi / other.hi;
+    let r = self - other * DoubleDouble::from_f64(q1);
+    let q2 = r.hi / other.hi;
+    let r = r - other * DoubleDouble::from_f64(q2);
+    let q3 = r.hi / other.hi;
+
+    let (hi, lo) = quick_two_sum(q1, q2);
+    DoubleDouble { hi, lo } + DoubleDouble::from_f64(q3)
+  }
+}
+
+impl Neg for DoubleDouble {
+  type Output = DoubleDouble;
+
+  #[inline(always)]
+  fn neg(self) -> DoubleDouble {
+    DoubleDouble { hi: -self.hi, lo: -self.lo }
+  }
+}
+
+impl PartialOrd for DoubleDouble {
+  #[inline(always)]
+  fn partial_cmp(&self, other: &DoubleDouble) -> Option<Ordering> {
+    match self.hi.partial_cmp(&other.hi) {
+      Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+      ordering => ordering,
+    }
+  }
+}
+
+impl Real for DoubleDouble {
+  #[inline(always)]
+  fn from_f64(value: f64) -> DoubleDouble {
+    DoubleDouble { hi: value, lo: 0.0 }
+  }
+
+  #[inline(always)]
+  fn to_f64(self) -> f64 {
+    self.hi + self.lo
+  }
+
+  #[inline(always)]
+  fn abs(self) -> DoubleDouble {
+    if self.hi < 0.0 { -self } else { self }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseDoubleDoubleError;
+
+// Parsed digit by digit so the full precision of the text is kept, f64::from_str would round to 17 digits
+impl FromStr for DoubleDouble {
+  type Err = ParseDoubleDoubleError;
+
+  fn from_str(text: &str) -> Result<DoubleDouble, ParseDoubleDoubleError> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+      Some(index) => (&text[..index], text[index + 1..].parse::<i32>().map_err(|_| ParseDoubleDoubleError)?),
+      None => (text, 0),
+    };
+
+    let mut value = DoubleDouble::from_f64(0.0);
+    let mut digits = 0;
+    let mut fraction_digits = 0;
+    let mut seen_point = false;
+
+    for c in mantissa.chars() {
+      match c {
+        '.' if !seen_point => seen_point = true,
+        '0'..='9' => {
+          value = value * DoubleDouble::from_f64(10.0) + DoubleDouble::from_f64(c.to_digit(10).unwrap() as f64);
+          digits += 1;
+          if seen_point {
+            fraction_digits += 1;
+          }
+        },
+        _ => return Err(ParseDoubleDoubleError),
+      }
+    }
+
+    if digits == 0 {
+      return Err(ParseDoubleDoubleError);
+    }
+
+    let value = value * DoubleDouble::powi10(exponent - fraction_digits);
+    return Ok(if negative { -value } else { value });
+  }
+}
@@ -1,58 +1,328 @@
-use crate::MAX_ITERATIONS;
+use crate::mandelbrot::formula::Formula;
+use crate::mandelbrot::parameters::Parameters;
+use crate::mandelbrot::real::Real;
+
+pub const DEFAULT_MAX_ITERATIONS: u32 = 1 << 15;
+pub const DEFAULT_BAILOUT: f64 = 2.0;
+// Stripes per turn round the origin for stripe average colouring
+pub const DEFAULT_STRIPE_DENSITY: f64 = 5.0;
+
+// Relative distance within which an orbit counts as back on its cycle, well above f32 rounding
+const CYCLE_TOLERANCE: f64 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EscapeStatus {
+    Escaped,
+    // Ran out of iterations without escaping
+    Interior,
+    // Caught in a cycle, or inside a component the formula knows of
+    Periodic,
+    // Settled on a root of a root-finding formula
+    Converged,
+}
+
+// Sees every z of an orbit. The escape loop is generic over it, so kernels built on the unit observer compile to
+// the plain loop
+pub trait Observer<T> {
+    fn observe(&mut self, x: T, y: T);
+}
+
+impl<T> Observer<T> for () {
+    #[inline(always)]
+    fn observe(&mut self, _x: T, _y: T) {}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trap {
+    Point((f64, f64)),
+    // Through a point, at an angle in radians from the real axis
+    Line((f64, f64), f64),
+    // Horizontal and vertical lines through a point
+    Cross((f64, f64)),
+    // Centre and radius
+    Circle((f64, f64), f64),
+    // Pickover stalks: the axes, only coloured where the orbit passes within the width
+    Stalks(f64),
+}
+
+impl Trap {
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        match *self {
+            Trap::Point((px, py)) => (x - px).hypot(y - py),
+            Trap::Line((px, py), angle) => ((x - px) * angle.sin() - (y - py) * angle.cos()).abs(),
+            Trap::Cross((px, py)) => (x - px).abs().min((y - py).abs()),
+            Trap::Circle((cx, cy), radius) => ((x - cx).hypot(y - cy) - radius).abs(),
+            Trap::Stalks(_) => x.abs().min(y.abs()),
+        }
+    }
+}
+
+// Closest approach of the orbit to the trap
+struct TrapObserver {
+    trap: Trap,
+    closest: f64,
+}
+
+impl<T: Real> Observer<T> for TrapObserver {
+    #[inline(always)]
+    fn observe(&mut self, x: T, y: T) {
+        self.closest = self.closest.min(self.trap.distance(x.to_f64(), y.to_f64()));
+    }
+}
+
+// Running mean of a per-iteration term, keeping the last term so the mean can be blended with the one an
+// iteration earlier
+#[derive(Clone, Copy, Debug, Default)]
+struct Average {
+    sum: f64,
+    last: f64,
+    count: u32,
+}
+
+impl Average {
+    fn add(&mut self, term: f64) {
+        self.sum += term;
+        self.last = term;
+        self.count += 1;
+    }
+
+    // Where the orbit only just escaped the fraction is near 0 and the full mean counts, near 1 it is close to
+    // escaping an iteration earlier and the mean without the last term counts
+    fn blend(&self, fraction: f64) -> Option<f64> {
+        if self.count < 2 {
+            return None;
+        }
+
+        let mean = self.sum / self.count as f64;
+        let previous = (self.sum - self.last) / (self.count - 1) as f64;
+        return Some(mean + (previous - mean) * fraction);
+    }
+}
+
+// Accumulates the triangle inequality average, where |z_n| lies between ||z_{n-1}|² - |c|| and |z_{n-1}|² + |c|,
+// and the stripe average of sin(density arg z_n) / 2 + 1 / 2. Either is None when nothing reads it
+struct AverageObserver {
+    c_modulus: f64,
+    density: f64,
+    previous_modulus: f64,
+    triangle: Option<Average>,
+    stripe: Option<Average>,
+}
+
+impl<T: Real> Observer<T> for AverageObserver {
+    #[inline(always)]
+    fn observe(&mut self, x: T, y: T) {
+        let (x, y) = (x.to_f64(), y.to_f64());
+        let modulus = x.hypot(y);
+
+        if let Some(triangle) = &mut self.triangle {
+            let squared = self.previous_modulus * self.previous_modulus;
+            let lower = (squared - self.c_modulus).abs();
+            let upper = squared + self.c_modulus;
+
+            // z_1 = c from the Mandelbrot set's z_0 = 0 has no range to lie in
+            if upper > lower {
+                triangle.add((modulus - lower) / (upper - lower));
+            }
+        }
+        if let Some(stripe) = &mut self.stripe {
+            stripe.add(0.5 * (self.density * y.atan2(x)).sin() + 0.5);
+        }
+
+        self.previous_modulus = modulus;
+    }
+}
+
+// The attracting cycle an interior point settles on
+#[derive(Clone, Copy, Debug)]
+pub struct Cycle {
+    // d(z_p)/dz once round the cycle, |λ| < 1 while it attracts
+    pub multiplier: (f64, f64),
+    // Interior distance estimate to the boundary on the complex plane, only derived for the Mandelbrot set
+    pub distance: Option<f64>,
+}
+
+impl Cycle {
+    // An exact repeat in floating point can be a multiple of the true period, with rounding carrying the orbit
+    // round the cycle more than once. Take the shortest divisor of it that comes back close to z.
+    pub fn period<F: Formula>(z: (f64, f64), c: (f64, f64), detected: u32, formula: &F) -> u32 {
+        let tolerance = CYCLE_TOLERANCE * (1.0 + z.0.hypot(z.1));
+        let mut w = z;
+
+        for period in 1..detected {
+            w = formula.step(w.0, w.1, w.0 * w.0, w.1 * w.1, c);
+            if detected.is_multiple_of(period) && (w.0 - z.0).hypot(w.1 - z.1) < tolerance {
+                return period;
+            }
+        }
+
+        return detected;
+    }
+
+    // Derivatives once round the cycle of z^d + c through z give the multiplier and, following Lobo, the interior distance
+    // (1 - |∂z|²) / |∂c∂z + ∂z∂z ∂c / (1 - ∂z)|. The distance is left out for Julia sets, whose pixels are not c
+    pub fn analyse<F: Formula>(z: (f64, f64), c: (f64, f64), period: u32, formula: &F) -> Cycle {
+        let exponent = formula.exponent();
+        let d = exponent.value();
+        let mut z = z;
+        let mut dz = (1.0, 0.0);
+        let mut dc = (0.0, 0.0);
+        let mut dzz = (0.0, 0.0);
+        let mut dcz = (0.0, 0.0);
+
+        for _ in 0..period {
+            // First and second derivatives of z^d at z
+            let first = scale(exponent.less(1).power(z.0, z.1), d);
+            let second = scale(exponent.less(2).power(z.0, z.1), d * (d - 1.0));
+
+            dcz = add(mul(second, mul(dc, dz)), mul(first, dcz));
+            dzz = add(mul(second, mul(dz, dz)), mul(first, dzz));
+            dc = add(mul(first, dc), (1.0, 0.0));
+            dz = mul(first, dz);
+            z = add(exponent.power(z.0, z.1), c);
+        }
+
+        let denominator = add(dcz, div(mul(dzz, dc), (1.0 - dz.0, -dz.1)));
+        let distance = (1.0 - dz.0 * dz.0 - dz.1 * dz.1) / denominator.0.hypot(denominator.1);
+
+        Cycle { multiplier: dz, distance: if formula.julia().is_some() { None } else { Some(distance) } }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EscapeResult<T> {
+    pub iterations: u32,
+    // Final z as (x, y)
+    pub z: (T, T),
+    // |z|^2 of the final z
+    pub magnitude: T,
+    // dz/dc, only tracked by kernels that need it
+    pub derivative: Option<(T, T)>,
+    pub status: EscapeStatus,
+    pub period: Option<u32>,
+    // Only analysed by the interior kernel
+    pub cycle: Option<Cycle>,
+    // Closest approach to the orbit trap, only tracked by the trap kernel
+    pub trap: Option<f64>,
+    // Triangle inequality and stripe averages of escaped orbits, only tracked by the average kernel
+    pub triangle: Option<f64>,
+    pub stripe: Option<f64>,
+    // Root the orbit converged to, only found by the Newton kernel
+    pub root: Option<u32>,
+}
+
+impl<T: Real> EscapeResult<T> {
+    fn new<F: Formula>(iterations: u32, x: T, y: T, magnitude: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+        let status = if iterations < params.max_iterations || magnitude.to_f64() > formula.bailout_squared() { EscapeStatus::Escaped } else { EscapeStatus::Interior };
+        EscapeResult { iterations, z: (x, y), magnitude, derivative: None, status, period: None, cycle: None, trap: None, triangle: None, stripe: None, root: None }
+    }
+
+    pub fn is_escaped(&self) -> bool {
+        self.status == EscapeStatus::Escaped
+    }
+
+    // Exterior distance estimate |z| ln|z| / |dz/dc| on the complex plane for escaped points of kernels that
+    // track the derivative, or the cycle's interior distance
+    pub fn distance(&self) -> Option<f64> {
+        if let Some(distance) = self.cycle.and_then(|cycle| cycle.distance) {
+            return Some(distance);
+        }
+
+        let (dx, dy) = self.derivative?;
+        if !self.is_escaped() {
+            return None;
+        }
+
+        let modulus = self.magnitude.to_f64().sqrt();
+        return Some(modulus * modulus.ln() / dx.to_f64().hypot(dy.to_f64()));
+    }
+
+    // Unit normal z / (dz/dc) of the potential's surface, for escaped points of kernels that track the derivative
+    pub fn normal(&self) -> Option<(f64, f64)> {
+        let (dx, dy) = self.derivative?;
+        if !self.is_escaped() {
+            return None;
+        }
+
+        let normal = div((self.z.0.to_f64(), self.z.1.to_f64()), (dx.to_f64(), dy.to_f64()));
+        let length = normal.0.hypot(normal.1);
+        if !(length > 0.0 && length.is_finite()) {
+            return None;
+        }
+
+        return Some((normal.0 / length, normal.1 / length));
+    }
+}
+
+pub fn escape_time<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    return escape_time_observed(y0, x0, formula, params, &mut ());
+}
+
+// The plain escape loop, handing every z to the observer
+pub fn escape_time_observed<T: Real, F: Formula, O: Observer<T>>(y0: T, x0: T, formula: &F, params: &Parameters, observer: &mut O) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
 
-pub fn escape_time(y0: f32, x0: f32) -> u32 {
     let mut iterations = 0;
 
-    let mut x = 0.0;
-    let mut y = 0.0;
-    let mut x2 = 0.0;
-    let mut y2 = 0.0;
-    while x2 + y2 <= 4.0 && iterations < MAX_ITERATIONS {
-        y = 2.0 * x * y + y0;
-        x = x2 - y2 + x0;
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
         x2 = x * x;
         y2 = y * y;
 
+        observer.observe(x, y);
         iterations += 1;
     }
 
-    return iterations;
+    return EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
 }
 
-pub fn escape_time_with_bulb(y0: f32, x0: f32) -> u32 {
-    let q = (x0 - 0.25) * (x0 - 0.25) + (y0 * y0);
-    let mut iterations = if q * (q + (x0 - 0.25)) <= 0.25 * y0 * y0 { MAX_ITERATIONS } else { 0 };
+pub fn escape_time_with_bulb<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
+    let known_period = formula.interior_period(y0, x0);
+    let mut iterations = if known_period.is_some() { max_iterations } else { 0 };
 
-    let mut x = 0.0;
-    let mut y = 0.0;
-    let mut x2 = 0.0;
-    let mut y2 = 0.0;
-    while x2 + y2 <= 4.0 && iterations < MAX_ITERATIONS {
-        y = 2.0 * x * y + y0;
-        x = x2 - y2 + x0;
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
         x2 = x * x;
         y2 = y * y;
 
         iterations += 1;
     }
 
-    return iterations;
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    if known_period.is_some() {
+        result.status = EscapeStatus::Periodic;
+        result.period = known_period;
+    }
+
+    return result;
 }
 
-pub fn escape_time_with_period(y0: f32, x0: f32) -> u32 {
+pub fn escape_time_with_period<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
     let mut iterations = 0;
 
     let mut period = 0;
-    let mut xold = 0.0;
-    let mut yold = 0.0;
-
-    let mut x = 0.0;
-    let mut y = 0.0;
-    let mut x2 = 0.0;
-    let mut y2 = 0.0;
-    while x2 + y2 <= 4.0 && iterations < MAX_ITERATIONS {
-        y = 2.0 * x * y + y0;
-        x = x2 - y2 + x0;
+    let mut cycle = None;
+
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    let mut xold = x;
+    let mut yold = y;
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
         x2 = x * x;
         y2 = y * y;
 
@@ -60,7 +330,8 @@ pub fn escape_time_with_period(y0: f32, x0: f32) -> u32 {
         period += 1;
 
         if x == xold && y == yold {
-            iterations = MAX_ITERATIONS;
+            iterations = max_iterations;
+            cycle = Some(period);
         } else if period == 60 {
             period = 0;
             xold = x;
@@ -68,25 +339,32 @@ pub fn escape_time_with_period(y0: f32, x0: f32) -> u32 {
         }
     }
 
-    return iterations;
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    if let Some(detected) = cycle {
+        result.status = EscapeStatus::Periodic;
+        result.period = Some(Cycle::period((x.to_f64(), y.to_f64()), (x0.to_f64(), y0.to_f64()), detected, formula));
+    }
+
+    return result;
 }
 
-pub fn escape_time_with_bulb_period(l_set: f32, r_set: f32) -> u32 {
-    let q = (r_set - 0.25) * (r_set - 0.25) + (l_set * l_set);
-    let mut iterations = if q * (q + (r_set - 0.25)) <= 0.25 * l_set * l_set { MAX_ITERATIONS } else { 0 };
+pub fn escape_time_with_bulb_period<T: Real, F: Formula>(l_set: T, r_set: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
 
-    let mut period = 0;
-    let mut r_old = 0.0;
-    let mut l_old = 0.0;
+    let known_period = formula.interior_period(l_set, r_set);
+    let mut iterations = if known_period.is_some() { max_iterations } else { 0 };
 
-    let mut r = 0.0;
-    let mut l = 0.0;
-    let mut r2 = 0.0;
-    let mut l2 = 0.0;
+    let mut period = 0;
+    let mut cycle = known_period;
+    let ((r_set, l_set), (mut r, mut l)) = formula.start(l_set, r_set);
+    let mut r2 = r * r;
+    let mut l2 = l * l;
+    let mut r_old = r;
+    let mut l_old = l;
 
-    while r2 + l2 <= 4.0 && iterations < MAX_ITERATIONS {
-        l = 2.0 * r * l + l_set;
-        r = r2 - l2 + r_set;
+    while r2 + l2 <= bailout && iterations < max_iterations {
+        (r, l) = formula.step(r, l, r2, l2, (r_set, l_set));
         r2 = r * r;
         l2 = l * l;
 
@@ -94,7 +372,8 @@ pub fn escape_time_with_bulb_period(l_set: f32, r_set: f32) -> u32 {
         period += 1;
 
         if r == r_old && l == l_old {
-            iterations = MAX_ITERATIONS;
+            iterations = max_iterations;
+            cycle = Some(period);
         } else if period == 60 {
             period = 0;
             r_old = r;
@@ -102,5 +381,272 @@ pub fn escape_time_with_bulb_period(l_set: f32, r_set: f32) -> u32 {
         }
     }
 
-    return iterations;
+    let mut result = EscapeResult::new(iterations, r, l, r2 + l2, formula, params);
+    if let Some(detected) = cycle {
+        result.status = EscapeStatus::Periodic;
+        result.period = Some(Cycle::period((r.to_f64(), l.to_f64()), (r_set.to_f64(), l_set.to_f64()), detected, formula));
+    }
+
+    return result;
+}
+
+// Escape time that also carries dz/dc, z'_{n+1} = d z_n^{d-1} z'_n + 1, for distance estimation and lighting
+pub fn escape_time_with_derivative<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
+    let mut iterations = 0;
+
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    let (mut dx, mut dy, offset) = formula.derivative_start();
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (dx, dy) = formula.derivative_step(x, y, dx, dy, offset);
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
+        x2 = x * x;
+        y2 = y * y;
+
+        iterations += 1;
+    }
+
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    result.derivative = Some((dx, dy));
+
+    return result;
+}
+
+// Escape time recording how close the orbit comes to params.trap
+pub fn escape_time_with_trap<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let mut observer = TrapObserver { trap: params.trap, closest: f64::INFINITY };
+
+    let mut result = escape_time_observed(y0, x0, formula, params, &mut observer);
+    result.trap = Some(observer.closest);
+
+    return result;
+}
+
+// Escape time with the triangle inequality and stripe averages the parameters ask for, blended by the fractional
+// part of the normalised iteration count so they run smoothly across iteration bands
+pub fn escape_time_with_average<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let channels = params.channels();
+    let (c, z) = formula.start(y0, x0);
+    let mut observer = AverageObserver {
+        c_modulus: c.0.to_f64().hypot(c.1.to_f64()),
+        density: params.stripe_density,
+        previous_modulus: z.0.to_f64().hypot(z.1.to_f64()),
+        triangle: if channels.triangle { Some(Average::default()) } else { None },
+        stripe: if channels.stripe { Some(Average::default()) } else { None },
+    };
+
+    let mut result = escape_time_observed(y0, x0, formula, params, &mut observer);
+    if result.is_escaped() {
+        let log_modulus = 0.5 * result.magnitude.to_f64().ln();
+        let fraction = formula.exponent().log(log_modulus / formula.escape_radius().ln()).clamp(0.0, 1.0);
+
+        result.triangle = observer.triangle.and_then(|triangle| triangle.blend(fraction));
+        result.stripe = observer.stripe.and_then(|stripe| stripe.blend(fraction));
+    }
+
+    return result;
+}
+
+// The derivative kernel with the known interior check, points inside have no exterior distance to estimate
+pub fn escape_time_with_distance<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
+    let known_period = formula.interior_period(y0, x0);
+    let mut iterations = if known_period.is_some() { max_iterations } else { 0 };
+
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    let (mut dx, mut dy, offset) = formula.derivative_start();
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (dx, dy) = formula.derivative_step(x, y, dx, dy, offset);
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
+        x2 = x * x;
+        y2 = y * y;
+
+        iterations += 1;
+    }
+
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    result.derivative = Some((dx, dy));
+    if known_period.is_some() {
+        result.status = EscapeStatus::Periodic;
+        result.period = known_period;
+    }
+
+    return result;
+}
+
+// The derivative kernel with the period kernel's cycle detection, analysing the cycle of interior points
+pub fn escape_time_with_interior<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
+    let mut iterations = 0;
+
+    let mut period = 0;
+    let mut cycle = None;
+
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    let (mut dx, mut dy, offset) = formula.derivative_start();
+    let mut xold = x;
+    let mut yold = y;
+    while x2 + y2 <= bailout && iterations < max_iterations {
+        (dx, dy) = formula.derivative_step(x, y, dx, dy, offset);
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
+        x2 = x * x;
+        y2 = y * y;
+
+        iterations += 1;
+        period += 1;
+
+        if x == xold && y == yold {
+            iterations = max_iterations;
+            cycle = Some(period);
+        } else if period == 60 {
+            period = 0;
+            xold = x;
+            yold = y;
+        }
+    }
+
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    result.derivative = Some((dx, dy));
+    if let Some(detected) = cycle {
+        let (z, c) = ((x.to_f64(), y.to_f64()), (x0.to_f64(), y0.to_f64()));
+        let period = Cycle::period(z, c, detected, formula);
+
+        result.status = EscapeStatus::Periodic;
+        result.period = Some(period);
+        result.cycle = Some(Cycle::analyse(z, c, period, formula));
+    }
+
+    return result;
+}
+
+// Iterates a root-finding formula from the pixel until it settles on a root, in place of the bailout test, with
+// the period kernel's cycle detection for orbits caught on an attracting cycle instead. Orbits that a vanishing
+// derivative throws off to infinity never settle either
+pub fn newton<T: Real, F: Formula>(y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+    let max_iterations = params.max_iterations;
+    let bailout = T::from_f64(formula.bailout_squared());
+
+    let mut iterations = 0;
+
+    let mut period = 0;
+    let mut cycle = None;
+
+    let ((x0, y0), (mut x, mut y)) = formula.start(y0, x0);
+    let mut x2 = x * x;
+    let mut y2 = y * y;
+    let mut xold = x;
+    let mut yold = y;
+    let mut root = formula.root(x, y);
+    while root.is_none() && x2 + y2 <= bailout && iterations < max_iterations {
+        (x, y) = formula.step(x, y, x2, y2, (x0, y0));
+        x2 = x * x;
+        y2 = y * y;
+
+        iterations += 1;
+        period += 1;
+        root = formula.root(x, y);
+
+        if x == xold && y == yold {
+            cycle = Some(period);
+            break;
+        } else if period == 60 {
+            period = 0;
+            xold = x;
+            yold = y;
+        }
+    }
+
+    let mut result = EscapeResult::new(iterations, x, y, x2 + y2, formula, params);
+    result.root = root;
+    if root.is_some() {
+        result.status = EscapeStatus::Converged;
+    } else {
+        result.iterations = max_iterations;
+        result.status = EscapeStatus::Interior;
+    }
+    if let (None, Some(detected)) = (root, cycle) {
+        result.status = EscapeStatus::Periodic;
+        result.period = Some(Cycle::period((x.to_f64(), y.to_f64()), (x0.to_f64(), y0.to_f64()), detected, formula));
+    }
+
+    return result;
+}
+
+// The kernels by name, for the command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    EscapeTime,
+    Bulb,
+    Period,
+    BulbPeriod,
+    Derivative,
+    Distance,
+    Interior,
+    Trap,
+    Average,
+    Newton,
+}
+
+// An escape kernel, one zero sized type per algorithm. The generators are built for the kernel as well as the float
+// type and formula, so every pixel calls straight into it
+pub trait Kernel: Copy + Send + Sync + 'static {
+    fn escape<T: Real, F: Formula>(self, y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T>;
+}
+
+macro_rules! kernels {
+    ($($kernel:ident => $escape:ident,)*) => {
+        $(
+            #[derive(Clone, Copy, Debug, Default)]
+            pub struct $kernel;
+
+            impl Kernel for $kernel {
+                #[inline(always)]
+                fn escape<T: Real, F: Formula>(self, y0: T, x0: T, formula: &F, params: &Parameters) -> EscapeResult<T> {
+                    $escape(y0, x0, formula, params)
+                }
+            }
+        )*
+    };
+}
+
+kernels! {
+    EscapeTimeKernel => escape_time,
+    BulbKernel => escape_time_with_bulb,
+    PeriodKernel => escape_time_with_period,
+    BulbPeriodKernel => escape_time_with_bulb_period,
+    DerivativeKernel => escape_time_with_derivative,
+    DistanceKernel => escape_time_with_distance,
+    InteriorKernel => escape_time_with_interior,
+    TrapKernel => escape_time_with_trap,
+    AverageKernel => escape_time_with_average,
+    NewtonKernel => newton,
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let norm = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / norm, (a.1 * b.0 - a.0 * b.1) / norm)
+}
+
+fn scale(a: (f64, f64), factor: f64) -> (f64, f64) {
+    (a.0 * factor, a.1 * factor)
 }
@@ -0,0 +1,669 @@
+use std::sync::Arc;
+
+use crate::mandelbrot::formula::{Exponent, Formula};
+use crate::mandelbrot::real::Real;
+
+// Most values the evaluation stack holds at once, far more than a formula typed on the command line needs
+const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Log,
+    Sqrt,
+    // |z| as a real number
+    Abs,
+    Conj,
+    Re,
+    Im,
+}
+
+const FUNCTIONS: [(&str, Function); 13] = [
+    ("sin", Function::Sin),
+    ("cos", Function::Cos),
+    ("tan", Function::Tan),
+    ("sinh", Function::Sinh),
+    ("cosh", Function::Cosh),
+    ("tanh", Function::Tanh),
+    ("exp", Function::Exp),
+    ("log", Function::Log),
+    ("sqrt", Function::Sqrt),
+    ("abs", Function::Abs),
+    ("conj", Function::Conj),
+    ("re", Function::Re),
+    ("im", Function::Im),
+];
+
+impl Function {
+    // The ones that only move components about stay at the kernel's precision, the rest go through f64
+    #[inline(always)]
+    fn apply<T: Real>(self, (x, y): (T, T)) -> (T, T) {
+        let zero = T::from_f64(0.0);
+
+        match self {
+            Function::Conj => return (x, -y),
+            Function::Re => return (x, zero),
+            Function::Im => return (y, zero),
+            _ => {},
+        }
+
+        let (x, y) = (x.to_f64(), y.to_f64());
+        let (re, im) = match self {
+            Function::Sin => (x.sin() * y.cosh(), x.cos() * y.sinh()),
+            Function::Cos => (x.cos() * y.cosh(), -x.sin() * y.sinh()),
+            Function::Tan => div(Function::Sin.apply((x, y)), Function::Cos.apply((x, y))),
+            Function::Sinh => (x.sinh() * y.cos(), x.cosh() * y.sin()),
+            Function::Cosh => (x.cosh() * y.cos(), x.sinh() * y.sin()),
+            Function::Tanh => div(Function::Sinh.apply((x, y)), Function::Cosh.apply((x, y))),
+            Function::Exp => (x.exp() * y.cos(), x.exp() * y.sin()),
+            Function::Log => (x.hypot(y).ln(), y.atan2(x)),
+            Function::Sqrt => Exponent::Real(0.5).power(x, y),
+            Function::Abs => (x.hypot(y), 0.0),
+            Function::Conj | Function::Re | Function::Im => unreachable!("handled at the kernel's precision"),
+        };
+
+        (T::from_f64(re), T::from_f64(im))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Power,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Z,
+    C,
+    Constant((f64, f64)),
+    Neg(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Box<Node>),
+}
+
+// Stack machine instructions an expression compiles to, operands are popped and the result pushed
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Z,
+    C,
+    Constant(f64, f64),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    // Powers with a constant exponent, whole ones multiplied out at the kernel's precision
+    Power(Exponent),
+    // Powers with a varying or complex exponent, w^v = exp(v log w) in f64
+    ComplexPower,
+    Function(Function),
+}
+
+// How f(-z) relates to f(z) for fixed c
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Parity {
+    Even,
+    Odd,
+    Neither,
+}
+
+impl Parity {
+    fn product(self, other: Parity) -> Parity {
+        match (self, other) {
+            (Parity::Neither, _) | (_, Parity::Neither) => Parity::Neither,
+            (a, b) if a == b => Parity::Even,
+            _ => Parity::Odd,
+        }
+    }
+}
+
+// A formula of z and c typed by the user, e.g. z^3 - z + c or sin(z)*c. Orbits start at z = c, since formulas
+// like sin(z)*c hold 0 fixed, and for those that don't it only shifts the iteration count by one
+#[derive(Clone, Debug)]
+pub struct Expression {
+    // Shared, so the clones each thread of a render takes don't copy it
+    program: Arc<[Op]>,
+    // The degree in z where the formula is a polynomial in it, for smooth values
+    exponent: Exponent,
+    mirrored: bool,
+    parity: Parity,
+    pub bailout: f64,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Expression, String> {
+        let node = parse_node(text)?;
+
+        let mut program = vec!();
+        compile(&node, &mut program);
+
+        // conj(z) has the conjugate orbit unless something tells the imaginary parts apart
+        let mirrored = program.iter().all(|op| match op {
+            Op::Constant(_, im) => *im == 0.0,
+            Op::Function(Function::Im) => false,
+            _ => true,
+        });
+
+        let exponent = match degree(&node) {
+            Some(d) if d >= 2.0 && d.fract() == 0.0 && d <= u32::MAX as f64 => Exponent::Integer(d as u32),
+            Some(d) if d.abs() > 1.0 => Exponent::Real(d),
+            // Not a polynomial in z, or one too flat to escape like one
+            _ => Exponent::Integer(2),
+        };
+
+        return Ok(Expression {
+            program: program.into(),
+            exponent,
+            mirrored,
+            parity: parity(&node),
+            bailout: 0.0,
+        });
+    }
+}
+
+// A complex number written with the same syntax, e.g. -0.5+0.866*i or exp(2*pi*i/3)
+pub fn parse_constant(text: &str) -> Result<(f64, f64), String> {
+    let node = parse_node(text)?;
+
+    let mut program = vec!();
+    compile(&node, &mut program);
+
+    match program[..] {
+        [Op::Constant(re, im)] => Ok((re, im)),
+        _ => Err(String::from("expected a constant, it cannot depend on z or c")),
+    }
+}
+
+fn parse_node(text: &str) -> Result<Node, String> {
+    let tokens = tokenise(text)?;
+    let mut parser = Parser { tokens: &tokens, next: 0 };
+
+    let node = parser.expression()?;
+    match parser.peek() {
+        Token { kind: Kind::End, .. } => {},
+        Token { kind: Kind::Symbol(')'), column } => return Err(format!("unmatched ')' at column {}", column)),
+        Token { column, .. } => return Err(format!("expected an operator at column {}, multiplying needs an explicit '*'", column)),
+    }
+
+    // Checked before compiling, which evaluates the constant parts on the same fixed stack
+    if depth(&node) > MAX_DEPTH {
+        return Err(format!("formula holds more than {} values at once, simplify it", MAX_DEPTH));
+    }
+
+    return Ok(node);
+}
+
+impl Formula for Expression {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, _x2: T, _y2: T, c: (T, T)) -> (T, T) {
+        evaluate(&self.program, (x, y), c)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout.max(self.exponent.escape_radius())
+    }
+
+    fn exponent(&self) -> Exponent {
+        self.exponent
+    }
+
+    fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    // z and -z have the same orbit, or orbits of opposite sign, when f(-z) = ±f(z)
+    fn is_julia_half_turn(&self) -> bool {
+        self.parity != Parity::Neither
+    }
+
+    // Orbits start at z = c, as 0 is a fixed point of formulas like sin(z)*c. For z^2 + c that is one step in,
+    // so the bands sit one iteration off the built-in formula's
+    #[inline(always)]
+    fn start<T: Real>(&self, y0: T, x0: T) -> ((T, T), (T, T)) {
+        ((x0, y0), (x0, y0))
+    }
+}
+
+#[inline(always)]
+fn evaluate<T: Real>(program: &[Op], z: (T, T), c: (T, T)) -> (T, T) {
+    let zero = T::from_f64(0.0);
+    let mut stack = [(zero, zero); MAX_DEPTH];
+    let mut top = 0;
+
+    for op in program {
+        match *op {
+            Op::Z | Op::C | Op::Constant(..) => {
+                stack[top] = match *op {
+                    Op::Z => z,
+                    Op::C => c,
+                    Op::Constant(re, im) => (T::from_f64(re), T::from_f64(im)),
+                    _ => unreachable!("only values are pushed"),
+                };
+                top += 1;
+            },
+            Op::Neg => stack[top - 1] = (-stack[top - 1].0, -stack[top - 1].1),
+            Op::Power(exponent) => stack[top - 1] = exponent.power(stack[top - 1].0, stack[top - 1].1),
+            Op::Function(function) => stack[top - 1] = function.apply(stack[top - 1]),
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::ComplexPower => {
+                top -= 1;
+                stack[top - 1] = binary(op, stack[top - 1], stack[top]);
+            },
+        }
+    }
+
+    return stack[0];
+}
+
+#[inline(always)]
+fn binary<T: Real>(op: &Op, (ax, ay): (T, T), (bx, by): (T, T)) -> (T, T) {
+    match op {
+        Op::Add => (ax + bx, ay + by),
+        Op::Sub => (ax - bx, ay - by),
+        Op::Mul => (ax * bx - ay * by, ax * by + ay * bx),
+        Op::Div => {
+            let norm = bx * bx + by * by;
+            ((ax * bx + ay * by) / norm, (ay * bx - ax * by) / norm)
+        },
+        Op::ComplexPower => {
+            let (w, v) = ((ax.to_f64(), ay.to_f64()), (bx.to_f64(), by.to_f64()));
+            // 0^v is taken as 0, as for the real exponents
+            let (re, im) = if w == (0.0, 0.0) { (0.0, 0.0) } else { Function::Exp.apply(mul(v, Function::Log.apply(w))) };
+            (T::from_f64(re), T::from_f64(im))
+        },
+        _ => unreachable!("only operators take two values"),
+    }
+}
+
+// Postfix order, with everything that doesn't depend on z or c folded into a constant
+fn compile(node: &Node, program: &mut Vec<Op>) {
+    let start = program.len();
+
+    match node {
+        Node::Z => program.push(Op::Z),
+        Node::C => program.push(Op::C),
+        Node::Constant((re, im)) => program.push(Op::Constant(*re, *im)),
+        Node::Neg(operand) => {
+            compile(operand, program);
+            program.push(Op::Neg);
+        },
+        Node::Call(function, argument) => {
+            compile(argument, program);
+            program.push(Op::Function(*function));
+        },
+        Node::Binary(Operator::Power, base, exponent) => {
+            compile(base, program);
+            let mut exponent_program = vec!();
+            compile(exponent, &mut exponent_program);
+
+            match exponent_program[..] {
+                [Op::Constant(d, 0.0)] if d >= 0.0 && d.fract() == 0.0 && d <= u32::MAX as f64 => {
+                    program.push(Op::Power(Exponent::Integer(d as u32)))
+                },
+                [Op::Constant(d, 0.0)] => program.push(Op::Power(Exponent::Real(d))),
+                _ => {
+                    program.append(&mut exponent_program);
+                    program.push(Op::ComplexPower);
+                },
+            }
+        },
+        Node::Binary(operator, left, right) => {
+            compile(left, program);
+            compile(right, program);
+            program.push(match operator {
+                Operator::Add => Op::Add,
+                Operator::Sub => Op::Sub,
+                Operator::Mul => Op::Mul,
+                Operator::Div => Op::Div,
+                Operator::Power => unreachable!("compiled above"),
+            });
+        },
+    }
+
+    let compiled = &program[start..];
+    if compiled.len() > 1 && !compiled.iter().any(|op| matches!(op, Op::Z | Op::C)) {
+        let (re, im) = evaluate(compiled, (0.0, 0.0), (0.0, 0.0));
+        program.truncate(start);
+        program.push(Op::Constant(re, im));
+    }
+}
+
+// Growth of |f| against |z| for large z where f is a polynomial in z, None where it isn't
+fn degree(node: &Node) -> Option<f64> {
+    match node {
+        Node::Z => Some(1.0),
+        Node::C | Node::Constant(_) => Some(0.0),
+        Node::Neg(operand) => degree(operand),
+        Node::Binary(operator, left, right) => {
+            let (left, right) = (degree(left)?, degree(right)?);
+
+            match operator {
+                Operator::Add | Operator::Sub => Some(left.max(right)),
+                Operator::Mul => Some(left + right),
+                Operator::Div if right == 0.0 => Some(left),
+                Operator::Div => None,
+                Operator::Power if right != 0.0 => None,
+                Operator::Power => exponent_value(node).map(|d| left * d),
+            }
+        },
+        // Folds keep the modulus, others only keep constants constant
+        Node::Call(Function::Abs | Function::Conj | Function::Re | Function::Im, argument) => degree(argument),
+        Node::Call(_, argument) => if degree(argument)? == 0.0 { Some(0.0) } else { None },
+    }
+}
+
+// Values on the stack at once while evaluating, counting power exponents even where they compile away
+fn depth(node: &Node) -> usize {
+    match node {
+        Node::Z | Node::C | Node::Constant(_) => 1,
+        Node::Neg(operand) | Node::Call(_, operand) => depth(operand),
+        Node::Binary(_, left, right) => depth(left).max(depth(right) + 1),
+    }
+}
+
+// The real exponent of a power node, where it is constant
+fn exponent_value(node: &Node) -> Option<f64> {
+    let exponent = match node {
+        Node::Binary(Operator::Power, _, exponent) => exponent,
+        _ => return None,
+    };
+    let mut program = vec!();
+    compile(exponent, &mut program);
+
+    match program[..] {
+        [Op::Constant(d, 0.0)] => Some(d),
+        _ => None,
+    }
+}
+
+fn parity(node: &Node) -> Parity {
+    match node {
+        Node::Z => Parity::Odd,
+        Node::C | Node::Constant(_) => Parity::Even,
+        Node::Neg(operand) | Node::Call(Function::Conj | Function::Re | Function::Im, operand) => parity(operand),
+        Node::Binary(Operator::Add | Operator::Sub, left, right) => {
+            let (left, right) = (parity(left), parity(right));
+            if left == right { left } else { Parity::Neither }
+        },
+        Node::Binary(Operator::Mul | Operator::Div, left, right) => parity(left).product(parity(right)),
+        Node::Binary(Operator::Power, base, _) => match (parity(base), exponent_value(node)) {
+            (Parity::Even, _) => Parity::Even,
+            (Parity::Odd, Some(d)) if d.fract() == 0.0 => if d % 2.0 == 0.0 { Parity::Even } else { Parity::Odd },
+            _ => Parity::Neither,
+        },
+        Node::Call(function, argument) => match (parity(argument), function) {
+            (Parity::Even, _) => Parity::Even,
+            (Parity::Odd, Function::Abs | Function::Cos | Function::Cosh) => Parity::Even,
+            (Parity::Odd, Function::Sin | Function::Tan | Function::Sinh | Function::Tanh) => Parity::Odd,
+            _ => Parity::Neither,
+        },
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Number(f64),
+    Identifier(String),
+    Symbol(char),
+    End,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: Kind,
+    // 1-based, for error messages
+    column: usize,
+}
+
+fn tokenise(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec!();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let column = index + 1;
+        let char = chars[index];
+
+        if char.is_whitespace() {
+            index += 1;
+        } else if char.is_ascii_digit() || char == '.' {
+            let mut end = index;
+            while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                end += 1;
+            }
+            // An exponent only where digits follow, so 2e is still 2 then e
+            if end < chars.len() && (chars[end] == 'e' || chars[end] == 'E') {
+                let sign = if end + 1 < chars.len() && (chars[end + 1] == '+' || chars[end + 1] == '-') { 1 } else { 0 };
+                if end + 1 + sign < chars.len() && chars[end + 1 + sign].is_ascii_digit() {
+                    end += 1 + sign;
+                    while end < chars.len() && chars[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                }
+            }
+
+            let number: String = chars[index..end].iter().collect();
+            let value = number.parse().map_err(|_| format!("invalid number '{}' at column {}", number, column))?;
+            tokens.push(Token { kind: Kind::Number(value), column });
+            index = end;
+        } else if char.is_alphabetic() || char == '_' {
+            let mut end = index;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+
+            tokens.push(Token { kind: Kind::Identifier(chars[index..end].iter().collect()), column });
+            index = end;
+        } else if "+-*/^()".contains(char) {
+            tokens.push(Token { kind: Kind::Symbol(char), column });
+            index += 1;
+        } else {
+            return Err(format!("unexpected character '{}' at column {}", char, column));
+        }
+    }
+
+    tokens.push(Token { kind: Kind::End, column: chars.len() + 1 });
+    return Ok(tokens);
+}
+
+// Recursive descent, loosest binding first:
+//   expression = term (('+' | '-') term)*
+//   term       = unary (('*' | '/') unary)*
+//   unary      = '-' unary | power
+//   power      = atom ('^' unary)?
+//   atom       = number | constant | variable | function '(' expression ')' | '(' expression ')'
+// so -z^2 is -(z^2) and z^2^3 is z^(2^3)
+struct Parser<'a> {
+    tokens: &'a [Token],
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> &Token {
+        let token = &self.tokens[self.next];
+        if token.kind != Kind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek().kind == Kind::Symbol(symbol)
+    }
+
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+
+        while self.is_symbol('+') || self.is_symbol('-') {
+            let operator = if self.is_symbol('+') { Operator::Add } else { Operator::Sub };
+            self.advance();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+
+        return Ok(node);
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+
+        while self.is_symbol('*') || self.is_symbol('/') {
+            let operator = if self.is_symbol('*') { Operator::Mul } else { Operator::Div };
+            self.advance();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+
+        return Ok(node);
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.is_symbol('-') {
+            self.advance();
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+
+        return self.power();
+    }
+
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+
+        if self.is_symbol('^') {
+            self.advance();
+            return Ok(Node::Binary(Operator::Power, Box::new(base), Box::new(self.unary()?)));
+        }
+
+        return Ok(base);
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let Token { kind, column } = self.advance().clone();
+
+        match kind {
+            Kind::Number(value) => Ok(Node::Constant((value, 0.0))),
+            Kind::Symbol('(') => {
+                let node = self.expression()?;
+                if !self.is_symbol(')') {
+                    return Err(format!("expected ')' at column {} to close the '(' at column {}", self.peek().column, column));
+                }
+                self.advance();
+                Ok(node)
+            },
+            Kind::Identifier(name) => {
+                if let Some((_, function)) = FUNCTIONS.iter().find(|(entry, _)| *entry == name) {
+                    if !self.is_symbol('(') {
+                        return Err(format!("{} at column {} needs its argument in parentheses", name, column));
+                    }
+                    return Ok(Node::Call(*function, Box::new(self.atom()?)));
+                }
+
+                match name.as_str() {
+                    "z" => Ok(Node::Z),
+                    "c" => Ok(Node::C),
+                    "i" => Ok(Node::Constant((0.0, 1.0))),
+                    "pi" => Ok(Node::Constant((std::f64::consts::PI, 0.0))),
+                    "e" => Ok(Node::Constant((std::f64::consts::E, 0.0))),
+                    _ => {
+                        let functions: Vec<&str> = FUNCTIONS.iter().map(|(entry, _)| *entry).collect();
+                        Err(format!(
+                            "unknown identifier '{}' at column {}, expected z, c, i, pi, e or a function: {}",
+                            name, column, functions.join(", ")
+                        ))
+                    },
+                }
+            },
+            Kind::End => Err(String::from("formula ends early, expected a number, variable or '('")),
+            Kind::Symbol(symbol) => Err(format!("unexpected '{}' at column {}, expected a number, variable or '('", symbol, column)),
+        }
+    }
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let norm = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / norm, (a.1 * b.0 - a.0 * b.1) / norm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot::formula::Mandelbrot;
+
+    fn error(text: &str) -> String {
+        Expression::parse(text).expect_err("formula is invalid")
+    }
+
+    #[test]
+    fn squaring_steps_like_the_mandelbrot_formula() {
+        let expression = Expression::parse("z^2 + c").unwrap();
+        let mandelbrot = Mandelbrot { bailout: 2.0 };
+
+        for &(x, y, c) in &[(0.3, -0.4, (-0.75, 0.1)), (-1.5, 0.25, (0.2, 0.6)), (0.0, 0.0, (-2.0, 0.0))] {
+            let expected = mandelbrot.step(x, y, x * x, y * y, c);
+            let (re, im) = expression.step(x, y, x * x, y * y, c);
+            assert!((re - expected.0).abs() < 1e-15 && (im - expected.1).abs() < 1e-15, "{:?} against {:?}", (re, im), expected);
+        }
+    }
+
+    #[test]
+    fn constants_fold_into_one() {
+        let expression = Expression::parse("2*3*z").unwrap();
+        assert_eq!(expression.program[..], [Op::Constant(6.0, 0.0), Op::Z, Op::Mul]);
+    }
+
+    #[test]
+    fn degree_and_symmetry_follow_the_formula() {
+        let cubic = Expression::parse("z^3 - z + c").unwrap();
+        assert_eq!(cubic.exponent(), Exponent::Integer(3));
+        assert!(cubic.is_mirrored());
+        assert!(!cubic.is_julia_half_turn());
+
+        // Not a polynomial in z, so it escapes as if squaring
+        let sine = Expression::parse("sin(z)*c").unwrap();
+        assert_eq!(sine.exponent(), Exponent::Integer(2));
+        assert!(sine.is_mirrored());
+        assert!(sine.is_julia_half_turn());
+
+        let conjugate = Expression::parse("conj(z)^2 + c").unwrap();
+        assert_eq!(conjugate.exponent(), Exponent::Integer(2));
+        assert!(conjugate.is_mirrored());
+        assert!(conjugate.is_julia_half_turn());
+
+        assert!(!Expression::parse("z^2 + c + i").unwrap().is_mirrored());
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(
+            error("foo(z)"),
+            "unknown identifier 'foo' at column 1, expected z, c, i, pi, e or a function: \
+             sin, cos, tan, sinh, cosh, tanh, exp, log, sqrt, abs, conj, re, im"
+        );
+        assert_eq!(error("(z^2 + c"), "expected ')' at column 9 to close the '(' at column 1");
+        assert_eq!(error("z^2 + c)"), "unmatched ')' at column 8");
+        assert_eq!(error("z^^2"), "unexpected '^' at column 3, expected a number, variable or '('");
+    }
+
+    #[test]
+    fn nesting_is_limited_to_the_stack() {
+        let nested = |values: usize| format!("{}z{}", "z + (".repeat(values - 1), ")".repeat(values - 1));
+
+        assert!(Expression::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(error(&nested(MAX_DEPTH + 1)), format!("formula holds more than {} values at once, simplify it", MAX_DEPTH));
+    }
+}
@@ -0,0 +1,363 @@
+use crate::mandelbrot::escape::DEFAULT_BAILOUT;
+use crate::mandelbrot::real::Real;
+
+// The d of z^d + c
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exponent {
+    // Powers by repeated multiplication, 2 is the Mandelbrot set
+    Integer(u32),
+    // Powers through the polar form in f64, for fractional and negative d
+    Real(f64),
+}
+
+impl Exponent {
+    pub fn value(self) -> f64 {
+        match self {
+            Exponent::Integer(d) => d as f64,
+            Exponent::Real(d) => d,
+        }
+    }
+
+    // Whether (-z)^d = z^d
+    pub fn is_even(self) -> bool {
+        match self {
+            Exponent::Integer(d) => d.is_multiple_of(2),
+            Exponent::Real(d) => d.rem_euclid(2.0) == 0.0,
+        }
+    }
+
+    // Smallest radius past which every orbit is bound to escape, |z|^d - |c| > |z| needs |z|^(d-1) > 2 for
+    // |c| up to |z|. Orbits of negative powers never run off to infinity, a large |z| only marks the orbit
+    // passing close to 0, so they keep the quadratic radius
+    pub fn escape_radius(self) -> f64 {
+        match self {
+            Exponent::Real(d) if d > 1.0 => 2f64.powf(1.0 / (d - 1.0)).max(DEFAULT_BAILOUT),
+            _ => DEFAULT_BAILOUT,
+        }
+    }
+
+    // Logarithm to base |d|, the factor the log modulus of an escaping orbit grows by each iteration
+    pub fn log(self, value: f64) -> f64 {
+        match self {
+            Exponent::Integer(2) => value.log2(),
+            _ => value.ln() / self.value().abs().ln(),
+        }
+    }
+
+    // d - amount, for the powers in the derivatives
+    pub fn less(self, amount: u32) -> Exponent {
+        match self {
+            Exponent::Integer(d) => Exponent::Integer(d - amount),
+            Exponent::Real(d) => Exponent::Real(d - amount as f64),
+        }
+    }
+
+    #[inline(always)]
+    pub fn power<T: Real>(self, x: T, y: T) -> (T, T) {
+        match self {
+            Exponent::Integer(d) => {
+                let (mut px, mut py) = (T::from_f64(1.0), T::from_f64(0.0));
+                for _ in 0..d {
+                    (px, py) = (px * x - py * y, px * y + py * x);
+                }
+                (px, py)
+            },
+            Exponent::Real(d) => {
+                let (x, y) = (x.to_f64(), y.to_f64());
+                let modulus = x.hypot(y);
+                // 0^d is taken as 0 for negative d too, so the Mandelbrot set's orbits still start at c
+                if modulus == 0.0 {
+                    return (T::from_f64(0.0), T::from_f64(0.0));
+                }
+
+                let (modulus, angle) = (modulus.powf(d), y.atan2(x) * d);
+                (T::from_f64(modulus * angle.cos()), T::from_f64(modulus * angle.sin()))
+            },
+        }
+    }
+}
+
+// What an orbit iterates, with everything it needs to know besides the pixel. The kernels and generators are
+// generic over it, so each formula compiles to its own loop rather than deciding on the formula every step.
+// The methods are generic over the float type so cycles found at the kernel's precision are refined in f64
+pub trait Formula: Clone + Send + Sync + 'static {
+    // One iteration, reusing the squares of x and y the kernels keep for the bailout test
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T);
+
+    // Radius past which an orbit has escaped, at least the bailout asked for
+    fn escape_radius(&self) -> f64;
+
+    fn bailout_squared(&self) -> f64 {
+        self.escape_radius() * self.escape_radius()
+    }
+
+    // The d the log modulus of an escaping orbit grows by, for smooth values
+    fn exponent(&self) -> Exponent {
+        Exponent::Integer(2)
+    }
+
+    // Whether conj(z) has the conjugate orbit, so the image is mirrored across the real axis
+    fn is_mirrored(&self) -> bool;
+
+    // Whether -z has the same orbit, so the image repeats rotated half a turn about the origin
+    fn is_half_turn(&self) -> bool {
+        false
+    }
+
+    // Whether the Julia sets of real c are mirrored, as they are wherever the set is
+    fn is_julia_mirrored(&self) -> bool {
+        self.is_mirrored()
+    }
+
+    // Whether the Julia sets repeat rotated half a turn. The built-in formulas all fold z^2, so z and -z share
+    // an orbit whenever the exponent is even
+    fn is_julia_half_turn(&self) -> bool {
+        self.exponent().is_even()
+    }
+
+    // Period of a component the point is known to lie in without iterating, e.g. the main cardioid
+    fn interior_period<T: Real>(&self, _y0: T, _x0: T) -> Option<u32> {
+        None
+    }
+
+    // Index of the root an orbit of a root-finding formula has settled on, None while it is still moving and
+    // for the escape-time formulas
+    fn root<T: Real>(&self, _x: T, _y: T) -> Option<u32> {
+        None
+    }
+
+    // c of the Julia set this renders, None for a Mandelbrot-style set
+    fn julia(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    // A Mandelbrot-style set iterates from z = 0 with c at the pixel. Returns (c, z_0)
+    #[inline(always)]
+    fn start<T: Real>(&self, y0: T, x0: T) -> ((T, T), (T, T)) {
+        ((x0, y0), (T::from_f64(0.0), T::from_f64(0.0)))
+    }
+
+    // The derivative with respect to the pixel starts at dc/dc = 0 and gains 1 a step. Returns (dx, dy, offset)
+    #[inline(always)]
+    fn derivative_start<T: Real>(&self) -> (T, T, T) {
+        (T::from_f64(0.0), T::from_f64(0.0), T::from_f64(1.0))
+    }
+
+    // z'_{n+1} = d z_n^{d-1} z'_n + offset. Only z^d + c has one, the command line keeps the other formulas off
+    // the derivative kernels
+    #[inline(always)]
+    fn derivative_step<T: Real>(&self, x: T, y: T, dx: T, dy: T, offset: T) -> (T, T) {
+        let exponent = self.exponent();
+        if exponent == Exponent::Integer(2) {
+            let two = T::from_f64(2.0);
+            return (two * (x * dx - y * dy) + offset, two * (x * dy + y * dx));
+        }
+
+        let d = T::from_f64(exponent.value());
+        let (px, py) = exponent.less(1).power(x, y);
+        let (kx, ky) = (d * px, d * py);
+        return (kx * dx - ky * dy + offset, kx * dy + ky * dx);
+    }
+}
+
+// z^2 + c
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mandelbrot {
+    pub bailout: f64,
+}
+
+impl Formula for Mandelbrot {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        (x2 - y2 + c.0, T::from_f64(2.0) * x * y + c.1)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout
+    }
+
+    fn is_mirrored(&self) -> bool {
+        true
+    }
+
+    // The main cardioid, where the fixed point attracts
+    #[inline(always)]
+    fn interior_period<T: Real>(&self, y0: T, x0: T) -> Option<u32> {
+        let quarter = T::from_f64(0.25);
+        let q = (x0 - quarter) * (x0 - quarter) + (y0 * y0);
+
+        if q * (q + (x0 - quarter)) <= quarter * y0 * y0 { Some(1) } else { None }
+    }
+}
+
+// z^d + c for any other d
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Multibrot {
+    pub exponent: Exponent,
+    pub bailout: f64,
+}
+
+impl Formula for Multibrot {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, _x2: T, _y2: T, c: (T, T)) -> (T, T) {
+        let (px, py) = self.exponent.power(x, y);
+        (px + c.0, py + c.1)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout.max(self.exponent.escape_radius())
+    }
+
+    fn exponent(&self) -> Exponent {
+        self.exponent
+    }
+
+    fn is_mirrored(&self) -> bool {
+        true
+    }
+}
+
+// (|x| + i|y|)^2 + c, the ship lies upside down with the imaginary axis upwards. Taking |xy| maps conj(c)
+// somewhere else, so it has no mirror
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BurningShip {
+    pub bailout: f64,
+}
+
+impl Formula for BurningShip {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        (x2 - y2 + c.0, T::from_f64(2.0) * (x * y).abs() + c.1)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout
+    }
+
+    fn is_mirrored(&self) -> bool {
+        false
+    }
+
+    // The fold takes |y|, so conj(z) lands on the same z as z after a step
+    fn is_julia_mirrored(&self) -> bool {
+        true
+    }
+}
+
+// conj(z)^2 + c, the Mandelbar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tricorn {
+    pub bailout: f64,
+}
+
+impl Formula for Tricorn {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        (x2 - y2 + c.0, c.1 - T::from_f64(2.0) * x * y)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout
+    }
+
+    fn is_mirrored(&self) -> bool {
+        true
+    }
+}
+
+// |Re z^2| + i Im z^2 + c
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Celtic {
+    pub bailout: f64,
+}
+
+impl Formula for Celtic {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        ((x2 - y2).abs() + c.0, T::from_f64(2.0) * x * y + c.1)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout
+    }
+
+    fn is_mirrored(&self) -> bool {
+        true
+    }
+}
+
+// |Re z^2| + i |Im z^2| + c, with no mirror for the same reason as the burning ship
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Buffalo {
+    pub bailout: f64,
+}
+
+impl Formula for Buffalo {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        ((x2 - y2).abs() + c.0, T::from_f64(2.0) * (x * y).abs() + c.1)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.bailout
+    }
+
+    fn is_mirrored(&self) -> bool {
+        false
+    }
+
+    // The fold takes |y|, so conj(z) lands on the same z as z after a step
+    fn is_julia_mirrored(&self) -> bool {
+        true
+    }
+}
+
+// The Julia set of c for a formula, iterating from z at the pixel with c fixed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Julia<F> {
+    pub formula: F,
+    pub c: (f64, f64),
+}
+
+impl<F: Formula> Formula for Julia<F> {
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, x2: T, y2: T, c: (T, T)) -> (T, T) {
+        self.formula.step(x, y, x2, y2, c)
+    }
+
+    fn escape_radius(&self) -> f64 {
+        self.formula.escape_radius()
+    }
+
+    fn exponent(&self) -> Exponent {
+        self.formula.exponent()
+    }
+
+    fn is_mirrored(&self) -> bool {
+        self.c.1 == 0.0 && self.formula.is_julia_mirrored()
+    }
+
+    fn is_half_turn(&self) -> bool {
+        self.formula.is_julia_half_turn()
+    }
+
+    fn julia(&self) -> Option<(f64, f64)> {
+        Some(self.c)
+    }
+
+    #[inline(always)]
+    fn start<T: Real>(&self, y0: T, x0: T) -> ((T, T), (T, T)) {
+        ((T::from_f64(self.c.0), T::from_f64(self.c.1)), (x0, y0))
+    }
+
+    // The derivative with respect to the starting z starts at dz/dz = 1 and gains nothing a step
+    #[inline(always)]
+    fn derivative_start<T: Real>(&self) -> (T, T, T) {
+        (T::from_f64(1.0), T::from_f64(0.0), T::from_f64(0.0))
+    }
+
+    #[inline(always)]
+    fn derivative_step<T: Real>(&self, x: T, y: T, dx: T, dy: T, offset: T) -> (T, T) {
+        self.formula.derivative_step(x, y, dx, dy, offset)
+    }
+}
@@ -0,0 +1,322 @@
+use std::ops::Range;
+
+use crate::mandelbrot::colour::smooth_iterations;
+use crate::mandelbrot::escape::EscapeResult;
+use crate::mandelbrot::formula::Exponent;
+use crate::mandelbrot::real::Real;
+
+// Per-pixel data kept alongside the iteration counts, only filled when something will read it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Channels {
+  // Normalised iteration counts
+  pub smooth: bool,
+  // Exterior distance estimates, needs a kernel that tracks dz/dc. Interior distances where the cycle is known
+  pub distance: bool,
+  // Cycle length of interior points
+  pub period: bool,
+  // Multiplier of the attracting cycle of interior points
+  pub multiplier: bool,
+  // Surface normals for lighting, needs dz/dc
+  pub normal: bool,
+  // Closest approach of each orbit to the orbit trap
+  pub trap: bool,
+  // Triangle inequality average of escaped orbits
+  pub triangle: bool,
+  // Stripe average of escaped orbits
+  pub stripe: bool,
+  // Root each Newton orbit converged to
+  pub root: bool,
+}
+
+impl Channels {
+  pub fn union(self, other: Channels) -> Channels {
+    Channels {
+      smooth: self.smooth || other.smooth,
+      distance: self.distance || other.distance,
+      period: self.period || other.period,
+      multiplier: self.multiplier || other.multiplier,
+      normal: self.normal || other.normal,
+      trap: self.trap || other.trap,
+      triangle: self.triangle || other.triangle,
+      stripe: self.stripe || other.stripe,
+      root: self.root || other.root,
+    }
+  }
+}
+
+// What the generators produce: escape data per pixel, coloured in a separate pass so a render can be recoloured
+// without iterating it again
+#[derive(Clone, Debug)]
+pub struct IterationBuffer {
+  pub width: usize,
+  pub height: usize,
+  pub max_iterations: u32,
+  // Width of a pixel on the complex plane, distances are stored in pixels
+  pub pixel_size: f64,
+  // d of z^d + c, normalising the smooth iteration counts
+  pub exponent: Exponent,
+  // Row-major from the top of the image, max_iterations where a pixel never escaped
+  pub iterations: Vec<u32>,
+  pub smooth: Option<Vec<f32>>,
+  // Distance to the boundary in pixels, 0 where there is no estimate
+  pub distance: Option<Vec<f32>>,
+  // 0 where no cycle was found
+  pub period: Option<Vec<u32>>,
+  // (|λ|, arg λ), 0 where no cycle was found
+  pub multiplier: Option<Vec<(f32, f32)>>,
+  // Unit normal of the potential's surface at escaped points, 0 elsewhere
+  pub normal: Option<Vec<(f32, f32)>>,
+  // On the complex plane, 0 where the orbit was not measured
+  pub trap: Option<Vec<f32>>,
+  // Blended averages, 0 where the orbit did not escape
+  pub triangle: Option<Vec<f32>>,
+  pub stripe: Option<Vec<f32>>,
+  // Numbered from 1, 0 where the orbit converged to no root
+  pub root: Option<Vec<u32>>,
+}
+
+// A run of pixels within one row, handed to the thread that computes them
+pub struct Pixels<'a> {
+  pub iterations: &'a mut [u32],
+  pub smooth: Option<&'a mut [f32]>,
+  pub distance: Option<&'a mut [f32]>,
+  pub period: Option<&'a mut [u32]>,
+  pub multiplier: Option<&'a mut [(f32, f32)]>,
+  pub normal: Option<&'a mut [(f32, f32)]>,
+  pub trap: Option<&'a mut [f32]>,
+  pub triangle: Option<&'a mut [f32]>,
+  pub stripe: Option<&'a mut [f32]>,
+  pub root: Option<&'a mut [u32]>,
+  pixel_size: f64,
+  exponent: Exponent,
+}
+
+impl<'a> Pixels<'a> {
+  pub fn len(&self) -> usize {
+    self.iterations.len()
+  }
+
+  pub fn set<T: Real>(&mut self, px: usize, result: &EscapeResult<T>) {
+    let (pixel_size, exponent) = (self.pixel_size, self.exponent);
+
+    self.iterations[px] = result.iterations;
+    if let Some(smooth) = &mut self.smooth {
+      smooth[px] = if result.is_escaped() { smooth_iterations(result, exponent) as f32 } else { 0.0 };
+    }
+    if let Some(distance) = &mut self.distance {
+      distance[px] = result.distance().map_or(0.0, |distance| (distance / pixel_size) as f32);
+    }
+    if let Some(period) = &mut self.period {
+      period[px] = result.period.unwrap_or(0);
+    }
+    if let Some(multiplier) = &mut self.multiplier {
+      multiplier[px] = result.cycle.map_or((0.0, 0.0), |cycle| {
+        let (x, y) = cycle.multiplier;
+        (x.hypot(y) as f32, y.atan2(x) as f32)
+      });
+    }
+    if let Some(normal) = &mut self.normal {
+      normal[px] = result.normal().map_or((0.0, 0.0), |(x, y)| (x as f32, y as f32));
+    }
+    if let Some(trap) = &mut self.trap {
+      trap[px] = result.trap.unwrap_or(0.0) as f32;
+    }
+    if let Some(triangle) = &mut self.triangle {
+      triangle[px] = result.triangle.unwrap_or(0.0) as f32;
+    }
+    if let Some(stripe) = &mut self.stripe {
+      stripe[px] = result.stripe.unwrap_or(0.0) as f32;
+    }
+    if let Some(root) = &mut self.root {
+      root[px] = result.root.map_or(0, |root| root + 1);
+    }
+  }
+
+  // Split into runs of at most size pixels
+  pub fn chunks(self, size: usize) -> Vec<Pixels<'a>> {
+    let (pixel_size, exponent) = (self.pixel_size, self.exponent);
+    let mut smooth = self.smooth.map(|smooth| smooth.chunks_mut(size));
+    let mut distance = self.distance.map(|distance| distance.chunks_mut(size));
+    let mut period = self.period.map(|period| period.chunks_mut(size));
+    let mut multiplier = self.multiplier.map(|multiplier| multiplier.chunks_mut(size));
+    let mut normal = self.normal.map(|normal| normal.chunks_mut(size));
+    let mut trap = self.trap.map(|trap| trap.chunks_mut(size));
+    let mut triangle = self.triangle.map(|triangle| triangle.chunks_mut(size));
+    let mut stripe = self.stripe.map(|stripe| stripe.chunks_mut(size));
+    let mut root = self.root.map(|root| root.chunks_mut(size));
+
+    self.iterations
+      .chunks_mut(size)
+      .map(|iterations| Pixels {
+        iterations,
+        smooth: smooth.as_mut().and_then(|chunks| chunks.next()),
+        distance: distance.as_mut().and_then(|chunks| chunks.next()),
+        period: period.as_mut().and_then(|chunks| chunks.next()),
+        multiplier: multiplier.as_mut().and_then(|chunks| chunks.next()),
+        normal: normal.as_mut().and_then(|chunks| chunks.next()),
+        trap: trap.as_mut().and_then(|chunks| chunks.next()),
+        triangle: triangle.as_mut().and_then(|chunks| chunks.next()),
+        stripe: stripe.as_mut().and_then(|chunks| chunks.next()),
+        root: root.as_mut().and_then(|chunks| chunks.next()),
+        pixel_size,
+        exponent,
+      })
+      .collect()
+  }
+}
+
+impl IterationBuffer {
+  pub fn new(width: usize, height: usize, max_iterations: u32, channels: Channels) -> IterationBuffer {
+    let pixels = width * height;
+
+    IterationBuffer {
+      width,
+      height,
+      max_iterations,
+      pixel_size: 1.0,
+      exponent: Exponent::Integer(2),
+      iterations: vec![max_iterations; pixels],
+      smooth: if channels.smooth { Some(vec![0.0; pixels]) } else { None },
+      distance: if channels.distance { Some(vec![0.0; pixels]) } else { None },
+      period: if channels.period { Some(vec![0; pixels]) } else { None },
+      multiplier: if channels.multiplier { Some(vec![(0.0, 0.0); pixels]) } else { None },
+      normal: if channels.normal { Some(vec![(0.0, 0.0); pixels]) } else { None },
+      trap: if channels.trap { Some(vec![0.0; pixels]) } else { None },
+      triangle: if channels.triangle { Some(vec![0.0; pixels]) } else { None },
+      stripe: if channels.stripe { Some(vec![0.0; pixels]) } else { None },
+      root: if channels.root { Some(vec![0; pixels]) } else { None },
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.iterations.len()
+  }
+
+  pub fn channels(&self) -> Channels {
+    Channels {
+      smooth: self.smooth.is_some(),
+      distance: self.distance.is_some(),
+      period: self.period.is_some(),
+      multiplier: self.multiplier.is_some(),
+      normal: self.normal.is_some(),
+      trap: self.trap.is_some(),
+      triangle: self.triangle.is_some(),
+      stripe: self.stripe.is_some(),
+      root: self.root.is_some(),
+    }
+  }
+
+  pub fn is_escaped(&self, index: usize) -> bool {
+    self.iterations[index] < self.max_iterations
+  }
+
+  pub fn set<T: Real>(&mut self, px: usize, py: usize, result: &EscapeResult<T>) {
+    let start = py * self.width;
+    self.pixels_mut(start..start + self.width).set(px, result);
+  }
+
+  // Copy one pixel's data onto another, e.g. to fill a cluster from its border
+  pub fn copy(&mut self, from: (usize, usize), to: (usize, usize)) {
+    let from = from.1 * self.width + from.0;
+    let to = to.1 * self.width + to.0;
+
+    self.copy_within(from..from + 1, to);
+  }
+
+  pub fn rows_mut(&mut self) -> Vec<Pixels<'_>> {
+    let width = self.width;
+    self.pixels_mut(0..self.len()).chunks(width)
+  }
+
+  // Place a single row buffer computed elsewhere at row py
+  pub fn copy_row(&mut self, py: usize, row: &IterationBuffer) {
+    let start = py * self.width;
+    let range = start..start + self.width;
+
+    self.iterations[range.clone()].copy_from_slice(&row.iterations);
+    copy_channel(&mut self.smooth, &row.smooth, range.clone());
+    copy_channel(&mut self.distance, &row.distance, range.clone());
+    copy_channel(&mut self.period, &row.period, range.clone());
+    copy_channel(&mut self.multiplier, &row.multiplier, range.clone());
+    copy_channel(&mut self.normal, &row.normal, range.clone());
+    copy_channel(&mut self.trap, &row.trap, range.clone());
+    copy_channel(&mut self.triangle, &row.triangle, range.clone());
+    copy_channel(&mut self.stripe, &row.stripe, range.clone());
+    copy_channel(&mut self.root, &row.root, range);
+  }
+
+  // Reflect the top half onto the bottom, for images symmetric about the real axis
+  pub fn mirror(&mut self) {
+    let width = self.width;
+
+    for row in 0..(self.height / 2) {
+      let opposite = self.height - row - 1;
+      self.copy_within(row * width..(row + 1) * width, opposite * width);
+    }
+  }
+
+  // Turn the top half half a turn onto the bottom, for images symmetric about the origin
+  pub fn rotate(&mut self) {
+    let (width, height) = (self.width, self.height);
+
+    for row in 0..(height / 2) {
+      for px in 0..width {
+        self.copy((px, row), (width - px - 1, height - row - 1));
+      }
+    }
+  }
+
+  fn pixels_mut(&mut self, range: Range<usize>) -> Pixels<'_> {
+    Pixels {
+      iterations: &mut self.iterations[range.clone()],
+      smooth: self.smooth.as_mut().map(|smooth| &mut smooth[range.clone()]),
+      distance: self.distance.as_mut().map(|distance| &mut distance[range.clone()]),
+      period: self.period.as_mut().map(|period| &mut period[range.clone()]),
+      multiplier: self.multiplier.as_mut().map(|multiplier| &mut multiplier[range.clone()]),
+      normal: self.normal.as_mut().map(|normal| &mut normal[range.clone()]),
+      trap: self.trap.as_mut().map(|trap| &mut trap[range.clone()]),
+      triangle: self.triangle.as_mut().map(|triangle| &mut triangle[range.clone()]),
+      stripe: self.stripe.as_mut().map(|stripe| &mut stripe[range.clone()]),
+      root: self.root.as_mut().map(|root| &mut root[range]),
+      pixel_size: self.pixel_size,
+      exponent: self.exponent,
+    }
+  }
+
+  fn copy_within(&mut self, from: Range<usize>, to: usize) {
+    self.iterations.copy_within(from.clone(), to);
+    if let Some(smooth) = &mut self.smooth {
+      smooth.copy_within(from.clone(), to);
+    }
+    if let Some(distance) = &mut self.distance {
+      distance.copy_within(from.clone(), to);
+    }
+    if let Some(period) = &mut self.period {
+      period.copy_within(from.clone(), to);
+    }
+    if let Some(multiplier) = &mut self.multiplier {
+      multiplier.copy_within(from.clone(), to);
+    }
+    if let Some(normal) = &mut self.normal {
+      normal.copy_within(from.clone(), to);
+    }
+    if let Some(trap) = &mut self.trap {
+      trap.copy_within(from.clone(), to);
+    }
+    if let Some(triangle) = &mut self.triangle {
+      triangle.copy_within(from.clone(), to);
+    }
+    if let Some(stripe) = &mut self.stripe {
+      stripe.copy_within(from.clone(), to);
+    }
+    if let Some(root) = &mut self.root {
+      root.copy_within(from, to);
+    }
+  }
+}
+
+fn copy_channel<V: Copy>(channel: &mut Option<Vec<V>>, row: &Option<Vec<V>>, range: Range<usize>) {
+  if let (Some(channel), Some(row)) = (channel, row) {
+    channel[range].copy_from_slice(row);
+  }
+}
@@ -2,436 +2,381 @@ use std::collections::VecDeque;
 use std::thread;
 use std::thread::JoinHandle;
 
-use crate::mandelbrot::colour::*;
-use crate::{SIZE_X, SIZE_Y, COLOUR_ARRAY_SIZE, COLOUR_ROW_SIZE, MAX_ITERATIONS};
+use crate::mandelbrot::big_fixed::BigFixed;
+use crate::mandelbrot::escape::Kernel;
+use crate::mandelbrot::formula::{Formula, Mandelbrot};
+use crate::mandelbrot::iteration_buffer::IterationBuffer;
+use crate::mandelbrot::parameters::Parameters;
+use crate::mandelbrot::perturbation::*;
+use crate::mandelbrot::real::Real;
+use crate::mandelbrot::viewport::{Symmetry, Viewport};
 
-const HALF_Y: usize = SIZE_Y / 2;
 const THREADS: usize = 8;
 
-const GRID_X: (f32, f32) = (-2.0, 1.0);
-const GRID_Y: (f32, f32) = (-1.0, 1.0);
+// Generators are built for each float type, formula and kernel, so the pixel loops call the kernel directly
+pub type Generator<T, F, K> = fn(&Viewport<T>, &Parameters, &F, K) -> IterationBuffer;
 
-const SCALE_X: f32 = (GRID_X.1 - GRID_X.0) / (SIZE_X - 1) as f32;
-const SCALE_Y: f32 = (GRID_Y.1 - GRID_Y.0) / (SIZE_Y - 1) as f32;
+fn new_buffer<T: Real, F: Formula>(viewport: &Viewport<T>, params: &Parameters, formula: &F) -> IterationBuffer {
+  new_rows(viewport, params, formula, viewport.height)
+}
+
+fn new_rows<T: Real, F: Formula>(viewport: &Viewport<T>, params: &Parameters, formula: &F, rows: usize) -> IterationBuffer {
+  let mut buffer = IterationBuffer::new(viewport.width, rows, params.max_iterations, params.channels());
+  buffer.pixel_size = viewport.pixel_size();
+  buffer.exponent = formula.exponent();
+
+  return buffer;
+}
 
-type EscapeAlgorithm = fn(f32, f32) -> u32;
+// Reflect or rotate the computed rows to complete a symmetric image
+fn mirror_half<T: Real>(buffer: &mut IterationBuffer, viewport: &Viewport<T>) {
+  match viewport.symmetry() {
+    Some(Symmetry::Mirror) => buffer.mirror(),
+    Some(Symmetry::Rotation) => buffer.rotate(),
+    None => {},
+  }
+}
 
-pub fn gms(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut mandelbrot_set = vec![0; COLOUR_ARRAY_SIZE];
-  for py in 0..(SIZE_Y) {
-    let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+pub fn gms<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = new_buffer(viewport, params, formula);
+  for py in 0..viewport.height {
+    let y0 = viewport.y0(py);
 
-    for px in 0..SIZE_X {
-      let x0 = SCALE_X * (px as f32) + GRID_X.0;
-      let iterations = algo(y0, x0);
-      colour_set(&mut mandelbrot_set, px, py, iterations);
+    for px in 0..viewport.width {
+      let x0 = viewport.x0(px);
+      let result = kernel.escape(y0, x0, formula, params);
+      buffer.set(px, py, &result);
     }
   }
 
-  return mandelbrot_set;
+  return buffer;
 }
 
-pub fn gms_half(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE];
+pub fn gms_half<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = new_buffer(viewport, params, formula);
 
-  for py in 0..HALF_Y {
-    let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+  for py in 0..viewport.computed_rows() {
+    let y0 = viewport.y0(py);
 
-    for px in 0..SIZE_X {
-      let x0 = SCALE_X * (px as f32) + GRID_X.0;
+    for px in 0..viewport.width {
+      let x0 = viewport.x0(px);
 
-      let iterations = algo(y0, x0);
-      colour_half(&mut set_colour, px, py, iterations);
+      let result = kernel.escape(y0, x0, formula, params);
+      buffer.set(px, py, &result);
     }
   }
 
-  return set_colour;
+  mirror_half(&mut buffer, viewport);
+
+  return buffer;
 }
 
-pub fn gms_cluster(algo: EscapeAlgorithm) -> Vec<u8> {
+pub fn gms_cluster<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
   const CLUSTER_SIZE: usize = 1 << 3;
 
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE];
+  let width = viewport.width;
+  let rows = viewport.computed_rows();
+  let mut buffer = new_buffer(viewport, params, formula);
 
-  for cy in 0..HALF_Y / CLUSTER_SIZE {
+  for cy in 0..rows.div_ceil(CLUSTER_SIZE) {
     let cy_offset_top = cy * CLUSTER_SIZE;
-    let cy_offset_bottom = cy_offset_top + CLUSTER_SIZE - 1;
+    let cy_offset_bottom = (cy_offset_top + CLUSTER_SIZE).min(rows) - 1;
 
-    for cx in 0..SIZE_X / CLUSTER_SIZE {
+    for cx in 0..width.div_ceil(CLUSTER_SIZE) {
       let cx_offset_left = cx * CLUSTER_SIZE;
-      let cx_offset_right = cx_offset_left + CLUSTER_SIZE - 1;
-
-      let c_y0 = SCALE_Y * cy_offset_top as f32 + GRID_Y.0;
-      let c_x0 = SCALE_X * cx_offset_left as f32 + GRID_X.0;
+      let cx_offset_right = (cx_offset_left + CLUSTER_SIZE).min(width) - 1;
 
-      let c_iterations = algo(c_y0, c_x0);
-      let c_colour = if c_iterations == MAX_ITERATIONS { 0 } else { 1 + (c_iterations - 1) % (COLOUR_R + COLOUR_G + COLOUR_B + 1) };
+      let c_y0 = viewport.y0(cy_offset_top);
+      let c_x0 = viewport.x0(cx_offset_left);
 
-      let r = (COLOUR_BRIGHTNESS * (c_colour & COLOUR_R) >> 4) as u8;
-      let g = (COLOUR_BRIGHTNESS * (c_colour & COLOUR_G) >> 2) as u8;
-      let b = (COLOUR_BRIGHTNESS * (c_colour & COLOUR_B)) as u8;
+      let c_iterations = kernel.escape(c_y0, c_x0, formula, params).iterations;
 
       let mut is_boxed = true;
 
       // Top
-      for px in 0..CLUSTER_SIZE {
-        let x0 = SCALE_X * ((cx_offset_left + px) as f32) + GRID_X.0;
-        let c_iterations = algo(c_y0, x0);
-        colour_half(&mut set_colour, cx_offset_left + px, cy_offset_top, c_iterations);
-
-        let p = 3 * (cy_offset_top * SIZE_X + cx_offset_left + px);
-        is_boxed &= r == set_colour[p] &&
-          g == set_colour[p + 1] &&
-          b == set_colour[p + 2];
+      for px in cx_offset_left..(cx_offset_right + 1) {
+        let x0 = viewport.x0(px);
+        let c_result = kernel.escape(c_y0, x0, formula, params);
+        buffer.set(px, cy_offset_top, &c_result);
+
+        is_boxed &= c_iterations == c_result.iterations;
       }
 
       // Bottom
-      for px in 0..CLUSTER_SIZE {
-        let y0 = SCALE_Y * cy_offset_bottom as f32 + GRID_Y.0;
-        let x0 = SCALE_X * ((cx_offset_left + px) as f32) + GRID_X.0;
-        let c_iterations = algo(y0, x0);
-        colour_half(&mut set_colour, cx_offset_left + px, cy_offset_bottom, c_iterations);
-
-        let p = 3 * (cy_offset_bottom * SIZE_X + cx_offset_left + px);
-        is_boxed &= r == set_colour[p] &&
-          g == set_colour[p + 1] &&
-          b == set_colour[p + 2];
+      for px in cx_offset_left..(cx_offset_right + 1) {
+        let y0 = viewport.y0(cy_offset_bottom);
+        let x0 = viewport.x0(px);
+        let c_result = kernel.escape(y0, x0, formula, params);
+        buffer.set(px, cy_offset_bottom, &c_result);
+
+        is_boxed &= c_iterations == c_result.iterations;
       }
 
       // Left
-      for py in 1..(CLUSTER_SIZE - 1) {
-        let y0 = SCALE_Y * ((cy_offset_top + py) as f32) + GRID_Y.0;
-        let c_iterations = algo(y0, c_x0);
-        colour_half(&mut set_colour, cx_offset_left, cy_offset_top + py, c_iterations);
-
-        let p = 3 * ((cy_offset_top + py) * SIZE_X + cx_offset_left);
-        is_boxed &= r == set_colour[p] &&
-          g == set_colour[p + 1] &&
-          b == set_colour[p + 2];
+      for py in (cy_offset_top + 1)..cy_offset_bottom {
+        let y0 = viewport.y0(py);
+        let c_result = kernel.escape(y0, c_x0, formula, params);
+        buffer.set(cx_offset_left, py, &c_result);
+
+        is_boxed &= c_iterations == c_result.iterations;
       }
 
       // Right
-      for py in 1..(CLUSTER_SIZE - 1) {
-        let y0 = SCALE_Y * ((cy_offset_top + py) as f32) + GRID_Y.0;
-        let x0 = SCALE_X * cx_offset_right as f32 + GRID_X.0;
-        let c_iterations = algo(y0, x0);
-        colour_half(&mut set_colour, cx_offset_right, cy_offset_top + py, c_iterations);
-
-        let p = 3 * ((cy_offset_top + py) * SIZE_X + cx_offset_right);
-        is_boxed &= r == set_colour[p] &&
-          g == set_colour[p + 1] &&
-          b == set_colour[p + 2];
+      for py in (cy_offset_top + 1)..cy_offset_bottom {
+        let y0 = viewport.y0(py);
+        let x0 = viewport.x0(cx_offset_right);
+        let c_result = kernel.escape(y0, x0, formula, params);
+        buffer.set(cx_offset_right, py, &c_result);
+
+        is_boxed &= c_iterations == c_result.iterations;
       }
 
       if is_boxed {
-        for py in 1..(CLUSTER_SIZE - 1) {
-          let cpy = cy_offset_top + py;
-
-          for px in 1..(CLUSTER_SIZE - 1) {
-            let cpx = cx_offset_left + px;
-
-            let p = 3 * (cpy * SIZE_X + cpx);
-            let pr = 3 * ((SIZE_Y - cpy - 1) * SIZE_X + cpx);
-
-            set_colour[p] = r;
-            set_colour[p + 1] = g;
-            set_colour[p + 2] = b;
-
-            set_colour[pr] = r;
-            set_colour[pr + 1] = g;
-            set_colour[pr + 2] = b;
+        for cpy in (cy_offset_top + 1)..cy_offset_bottom {
+          for cpx in (cx_offset_left + 1)..cx_offset_right {
+            buffer.copy((cx_offset_left, cy_offset_top), (cpx, cpy));
           }
         }
       } else {
-        for py in 1..(CLUSTER_SIZE - 1) {
-          let cpy = cy_offset_top + py;
-          let y0 = SCALE_Y * (cpy as f32) + GRID_Y.0;
+        for cpy in (cy_offset_top + 1)..cy_offset_bottom {
+          let y0 = viewport.y0(cpy);
 
-          for px in 1..(CLUSTER_SIZE - 1) {
-            let cpx = cx_offset_left + px;
-            let x0 = SCALE_X * (cpx as f32) + GRID_X.0;
+          for cpx in (cx_offset_left + 1)..cx_offset_right {
+            let x0 = viewport.x0(cpx);
 
-            let iterations = algo(y0, x0);
-            colour_half(&mut set_colour, cpx, cpy, iterations);
+            let result = kernel.escape(y0, x0, formula, params);
+            buffer.set(cpx, cpy, &result);
           }
         }
       }
     }
   }
 
-  return set_colour;
+  mirror_half(&mut buffer, viewport);
+
+  return buffer;
 }
 
 // No time difference
-pub fn gms_cluster_simplified(algo: EscapeAlgorithm) -> Vec<u8> {
+pub fn gms_cluster_simplified<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
   const CLUSTER_SIZE: usize = 1 << 3;
 
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE];
+  let width = viewport.width;
+  let rows = viewport.computed_rows();
+  let mut buffer = new_buffer(viewport, params, formula);
 
-  for cluster_y in 0..HALF_Y / CLUSTER_SIZE {
+  for cluster_y in 0..rows.div_ceil(CLUSTER_SIZE) {
     let pixel_y_top = cluster_y * CLUSTER_SIZE;
-    let pixel_y_bottom = pixel_y_top + CLUSTER_SIZE - 1;
+    let pixel_y_bottom = (pixel_y_top + CLUSTER_SIZE).min(rows) - 1;
 
-    for cluster_x in 0..SIZE_X / CLUSTER_SIZE {
+    for cluster_x in 0..width.div_ceil(CLUSTER_SIZE) {
       let pixel_x_left = cluster_x * CLUSTER_SIZE;
-      let pixel_x_right = pixel_x_left + CLUSTER_SIZE - 1;
+      let pixel_x_right = (pixel_x_left + CLUSTER_SIZE).min(width) - 1;
 
-      let cluster_y0 = SCALE_Y * pixel_y_top as f32 + GRID_Y.0;
-      let cluster_x0 = SCALE_X * pixel_x_left as f32 + GRID_X.0;
+      let cluster_y0 = viewport.y0(pixel_y_top);
+      let cluster_x0 = viewport.x0(pixel_x_left);
 
-      let cluster_iterations = algo(cluster_y0, cluster_x0);
-      let (cluster_r, cluster_g, cluster_b) = colour(cluster_iterations);
+      let cluster_iterations = kernel.escape(cluster_y0, cluster_x0, formula, params).iterations;
 
       let mut is_boxed = true;
 
       // Top / Bottom
-      for pixel_x in pixel_x_left..(pixel_x_left + CLUSTER_SIZE) {
-        let x0 = SCALE_X * pixel_x as f32 + GRID_X.0;
-        let y0 = SCALE_Y * pixel_y_bottom as f32 + GRID_Y.0;
-
-        let top_iterations = algo(cluster_y0, x0);
-        let bottom_iterations = algo(y0, x0);
+      for pixel_x in pixel_x_left..(pixel_x_right + 1) {
+        let x0 = viewport.x0(pixel_x);
+        let y0 = viewport.y0(pixel_y_bottom);
 
-        colour_half(&mut set_colour, pixel_x, pixel_y_top, top_iterations);
-        colour_half(&mut set_colour, pixel_x, pixel_y_bottom, bottom_iterations);
+        let top_result = kernel.escape(cluster_y0, x0, formula, params);
+        let bottom_result = kernel.escape(y0, x0, formula, params);
 
-        let pixel_top = 3 * (pixel_y_top * SIZE_X + pixel_x);
-        is_boxed &= cluster_r == set_colour[pixel_top] &&
-          cluster_g == set_colour[pixel_top + 1] &&
-          cluster_b == set_colour[pixel_top + 2];
+        buffer.set(pixel_x, pixel_y_top, &top_result);
+        buffer.set(pixel_x, pixel_y_bottom, &bottom_result);
 
-        let pixel_bottom = 3 * (pixel_y_bottom * SIZE_X + pixel_x);
-        is_boxed &= cluster_r == set_colour[pixel_bottom] &&
-          cluster_g == set_colour[pixel_bottom + 1] &&
-          cluster_b == set_colour[pixel_bottom + 2];
+        is_boxed &= cluster_iterations == top_result.iterations &&
+          cluster_iterations == bottom_result.iterations;
       }
 
       // Left / Right
       for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
-        let y0 = SCALE_Y * pixel_y as f32 + GRID_Y.0;
-        let x0 = SCALE_X * pixel_x_right as f32 + GRID_X.0;
-
-        let left_iterations = algo(y0, cluster_x0);
-        let right_iterations = algo(y0, x0);
+        let y0 = viewport.y0(pixel_y);
+        let x0 = viewport.x0(pixel_x_right);
 
-        colour_half(&mut set_colour, pixel_x_left, pixel_y, left_iterations);
-        colour_half(&mut set_colour, pixel_x_right, pixel_y, right_iterations);
+        let left_result = kernel.escape(y0, cluster_x0, formula, params);
+        let right_result = kernel.escape(y0, x0, formula, params);
 
-        let pixel_left = 3 * (pixel_y * SIZE_X + pixel_x_left);
-        is_boxed &= cluster_r == set_colour[pixel_left] &&
-          cluster_g == set_colour[pixel_left + 1] &&
-          cluster_b == set_colour[pixel_left + 2];
+        buffer.set(pixel_x_left, pixel_y, &left_result);
+        buffer.set(pixel_x_right, pixel_y, &right_result);
 
-        let pixel_right = 3 * (pixel_y * SIZE_X + pixel_x_right);
-        is_boxed &= cluster_r == set_colour[pixel_right] &&
-          cluster_g == set_colour[pixel_right + 1] &&
-          cluster_b == set_colour[pixel_right + 2];
+        is_boxed &= cluster_iterations == left_result.iterations &&
+          cluster_iterations == right_result.iterations;
       }
 
       if is_boxed {
         for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
           for pixel_x in (pixel_x_left + 1)..(pixel_x_right) {
-            let pixel = 3 * (pixel_y * SIZE_X + pixel_x);
-            let reverse_pixel = 3 * ((SIZE_Y - pixel_y - 1) * SIZE_X + pixel_x);
-
-            set_colour[pixel] = cluster_r;
-            set_colour[pixel + 1] = cluster_g;
-            set_colour[pixel + 2] = cluster_b;
-
-            set_colour[reverse_pixel] = cluster_r;
-            set_colour[reverse_pixel + 1] = cluster_g;
-            set_colour[reverse_pixel + 2] = cluster_b;
+            buffer.copy((pixel_x_left, pixel_y_top), (pixel_x, pixel_y));
           }
         }
       } else {
         for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
-          let y0 = SCALE_Y * pixel_y as f32 + GRID_Y.0;
+          let y0 = viewport.y0(pixel_y);
 
           for pixel_x in (pixel_x_left + 1)..(pixel_x_right) {
-            let x0 = SCALE_X * pixel_x as f32 + GRID_X.0;
+            let x0 = viewport.x0(pixel_x);
 
-            let iterations = algo(y0, x0);
-            colour_half(&mut set_colour, pixel_x, pixel_y, iterations);
+            let result = kernel.escape(y0, x0, formula, params);
+            buffer.set(pixel_x, pixel_y, &result);
           }
         }
       }
     }
   }
 
-  return set_colour;
+  mirror_half(&mut buffer, viewport);
+
+  return buffer;
 }
 
 // Better with larger images - Make recursive
-pub fn gms_cluster_checkered(algo: EscapeAlgorithm) -> Vec<u8> {
+pub fn gms_cluster_checkered<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
   const CLUSTER_SIZE: usize = 1 << 5;
 
-  let mut mandelbrot_set_colour = vec![0; COLOUR_ARRAY_SIZE / 2];
-
-  fn compute_cluster(algo: EscapeAlgorithm, mut set_colour: &mut Vec<u8>, pixel_y_top: usize, pixel_y_bottom: usize, pixel_x_left: usize, pixel_x_right: usize) {
-    let cluster_y0 = SCALE_Y * pixel_y_top as f32 + GRID_Y.0;
-    let cluster_x0 = SCALE_X * pixel_x_left as f32 + GRID_X.0;
+  let width = viewport.width;
+  let rows = viewport.computed_rows();
+  let mut buffer = new_buffer(viewport, params, formula);
 
-    let cluster_iterations = algo(cluster_y0, cluster_x0);
-    let colours = colour(cluster_iterations);
+  #[allow(clippy::too_many_arguments)]
+  fn compute_cluster<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K, buffer: &mut IterationBuffer, pixel_y_top: usize, pixel_y_bottom: usize, pixel_x_left: usize, pixel_x_right: usize) {
+    let cluster_y0 = viewport.y0(pixel_y_top);
+    let cluster_x0 = viewport.x0(pixel_x_left);
 
-    let (cluster_r, cluster_g, cluster_b) = colours;
+    let cluster_iterations = kernel.escape(cluster_y0, cluster_x0, formula, params).iterations;
 
     let mut is_boxed = true;
 
     // Top / Bottom
     for pixel_x in pixel_x_left..(pixel_x_right + 1) {
-      let x0 = SCALE_X * pixel_x as f32 + GRID_X.0;
-      let y0 = SCALE_Y * pixel_y_bottom as f32 + GRID_Y.0;
-
-      let top_iterations = algo(cluster_y0, x0);
-      let bottom_iterations = algo(y0, x0);
+      let x0 = viewport.x0(pixel_x);
+      let y0 = viewport.y0(pixel_y_bottom);
 
-      colour_set(&mut set_colour, pixel_x, pixel_y_top, top_iterations);
-      colour_set(&mut set_colour, pixel_x, pixel_y_bottom, bottom_iterations);
+      let top_result = kernel.escape(cluster_y0, x0, formula, params);
+      let bottom_result = kernel.escape(y0, x0, formula, params);
 
-      let pixel_top = 3 * (pixel_y_top * SIZE_X + pixel_x);
-      is_boxed &= cluster_r == set_colour[pixel_top] &&
-        cluster_g == set_colour[pixel_top + 1] &&
-        cluster_b == set_colour[pixel_top + 2];
+      buffer.set(pixel_x, pixel_y_top, &top_result);
+      buffer.set(pixel_x, pixel_y_bottom, &bottom_result);
 
-      let pixel_bottom = 3 * (pixel_y_bottom * SIZE_X + pixel_x);
-      is_boxed &= cluster_r == set_colour[pixel_bottom] &&
-        cluster_g == set_colour[pixel_bottom + 1] &&
-        cluster_b == set_colour[pixel_bottom + 2];
+      is_boxed &= cluster_iterations == top_result.iterations &&
+        cluster_iterations == bottom_result.iterations;
     }
 
     // Left / Right
     for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
-      let y0 = SCALE_Y * pixel_y as f32 + GRID_Y.0;
-      let x0 = SCALE_X * pixel_x_right as f32 + GRID_X.0;
+      let y0 = viewport.y0(pixel_y);
+      let x0 = viewport.x0(pixel_x_right);
 
-      let left_iterations = algo(y0, cluster_x0);
-      let right_iterations = algo(y0, x0);
+      let left_result = kernel.escape(y0, cluster_x0, formula, params);
+      let right_result = kernel.escape(y0, x0, formula, params);
 
-      colour_set(&mut set_colour, pixel_x_left, pixel_y, left_iterations);
-      colour_set(&mut set_colour, pixel_x_right, pixel_y, right_iterations);
+      buffer.set(pixel_x_left, pixel_y, &left_result);
+      buffer.set(pixel_x_right, pixel_y, &right_result);
 
-      let pixel_left = 3 * (pixel_y * SIZE_X + pixel_x_left);
-      is_boxed &= cluster_r == set_colour[pixel_left] &&
-        cluster_g == set_colour[pixel_left + 1] &&
-        cluster_b == set_colour[pixel_left + 2];
-
-      let pixel_right = 3 * (pixel_y * SIZE_X + pixel_x_right);
-      is_boxed &= cluster_r == set_colour[pixel_right] &&
-        cluster_g == set_colour[pixel_right + 1] &&
-        cluster_b == set_colour[pixel_right + 2];
+      is_boxed &= cluster_iterations == left_result.iterations &&
+        cluster_iterations == right_result.iterations;
     }
 
     if is_boxed {
       for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
         for pixel_x in (pixel_x_left + 1)..(pixel_x_right) {
-          let pixel = 3 * (pixel_y * SIZE_X + pixel_x);
-
-          set_colour[pixel] = cluster_r;
-          set_colour[pixel + 1] = cluster_g;
-          set_colour[pixel + 2] = cluster_b;
+          buffer.copy((pixel_x_left, pixel_y_top), (pixel_x, pixel_y));
         }
       }
-    } else {
-      if pixel_y_bottom - pixel_y_top < (1 << 2) {
-        for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
-          let y0 = SCALE_Y * pixel_y as f32 + GRID_Y.0;
+    } else if pixel_y_bottom - pixel_y_top < (1 << 2) || pixel_x_right - pixel_x_left < (1 << 2) {
+      for pixel_y in (pixel_y_top + 1)..(pixel_y_bottom) {
+        let y0 = viewport.y0(pixel_y);
 
-          for pixel_x in (pixel_x_left + 1)..(pixel_x_right) {
-            let x0 = SCALE_X * pixel_x as f32 + GRID_X.0;
+        for pixel_x in (pixel_x_left + 1)..(pixel_x_right) {
+          let x0 = viewport.x0(pixel_x);
 
-            let iterations = algo(y0, x0);
-            colour_set(&mut set_colour, pixel_x, pixel_y, iterations);
-          }
+          let result = kernel.escape(y0, x0, formula, params);
+          buffer.set(pixel_x, pixel_y, &result);
         }
-      } else {
-        let pixel_y_mid = (pixel_y_top + pixel_y_bottom) / 2;
-        let pixel_x_mid = (pixel_x_left + pixel_x_right) / 2;
-
-        compute_cluster(algo, &mut set_colour, pixel_y_top + 1, pixel_y_mid, pixel_x_left + 1, pixel_x_mid);
-        compute_cluster(algo, &mut set_colour, pixel_y_top + 1, pixel_y_mid, pixel_x_mid + 1, pixel_x_right - 1);
-        compute_cluster(algo, &mut set_colour, pixel_y_mid + 1, pixel_y_bottom - 1, pixel_x_left + 1, pixel_x_mid);
-        compute_cluster(algo, &mut set_colour, pixel_y_mid + 1, pixel_y_bottom - 1, pixel_x_mid + 1, pixel_x_right - 1);
       }
+    } else {
+      let pixel_y_mid = (pixel_y_top + pixel_y_bottom) / 2;
+      let pixel_x_mid = (pixel_x_left + pixel_x_right) / 2;
+
+      compute_cluster(viewport, params, formula, kernel, buffer, pixel_y_top + 1, pixel_y_mid, pixel_x_left + 1, pixel_x_mid);
+      compute_cluster(viewport, params, formula, kernel, buffer, pixel_y_top + 1, pixel_y_mid, pixel_x_mid + 1, pixel_x_right - 1);
+      compute_cluster(viewport, params, formula, kernel, buffer, pixel_y_mid + 1, pixel_y_bottom - 1, pixel_x_left + 1, pixel_x_mid);
+      compute_cluster(viewport, params, formula, kernel, buffer, pixel_y_mid + 1, pixel_y_bottom - 1, pixel_x_mid + 1, pixel_x_right - 1);
     }
   }
 
-  for cluster_y in 0..HALF_Y / CLUSTER_SIZE {
+  let clusters_y = rows.div_ceil(CLUSTER_SIZE);
+  let clusters_x = width.div_ceil(CLUSTER_SIZE);
+
+  for cluster_y in 0..clusters_y {
     let pixel_y_top = cluster_y * CLUSTER_SIZE;
-    let pixel_y_bottom = pixel_y_top + CLUSTER_SIZE - 1;
+    let pixel_y_bottom = (pixel_y_top + CLUSTER_SIZE).min(rows) - 1;
 
-    for cluster_x in ((cluster_y % 2)..SIZE_X / CLUSTER_SIZE).step_by(2) {
+    for cluster_x in ((cluster_y % 2)..clusters_x).step_by(2) {
       let pixel_x_left = cluster_x * CLUSTER_SIZE;
-      let pixel_x_right = pixel_x_left + CLUSTER_SIZE - 1;
+      let pixel_x_right = (pixel_x_left + CLUSTER_SIZE).min(width) - 1;
 
-      compute_cluster(algo, &mut mandelbrot_set_colour, pixel_y_top, pixel_y_bottom, pixel_x_left, pixel_x_right)
+      compute_cluster(viewport, params, formula, kernel, &mut buffer, pixel_y_top, pixel_y_bottom, pixel_x_left, pixel_x_right)
     }
   }
 
-  for cluster_y in 0..HALF_Y / CLUSTER_SIZE {
+  for cluster_y in 0..clusters_y {
     let pixel_y_top = cluster_y * CLUSTER_SIZE;
-    let pixel_y_bottom = pixel_y_top + CLUSTER_SIZE - 1;
+    let pixel_y_bottom = (pixel_y_top + CLUSTER_SIZE).min(rows) - 1;
 
-    for cluster_x in (((cluster_y + 1) % 2)..SIZE_X / CLUSTER_SIZE).step_by(2) {
+    for cluster_x in (((cluster_y + 1) % 2)..clusters_x).step_by(2) {
       let pixel_x_left = cluster_x * CLUSTER_SIZE;
-      let pixel_x_right = pixel_x_left + CLUSTER_SIZE - 1;
+      let pixel_x_right = (pixel_x_left + CLUSTER_SIZE).min(width) - 1;
 
-      let cluster_y0 = SCALE_Y * pixel_y_top as f32 + GRID_Y.0;
-      let cluster_x0 = SCALE_X * pixel_x_left as f32 + GRID_X.0;
+      let cluster_y0 = viewport.y0(pixel_y_top);
+      let cluster_x0 = viewport.x0(pixel_x_left);
 
-      let cluster_iterations = algo(cluster_y0, cluster_x0);
-      let colours = colour(cluster_iterations);
+      let cluster_result = kernel.escape(cluster_y0, cluster_x0, formula, params);
+      let cluster_iterations = cluster_result.iterations;
 
       {
         let mut is_checkered_boxed = true;
 
         if cluster_y != 0 {
           for pixel_x in pixel_x_left..pixel_x_right {
-            let pixel_top = 3 * ((pixel_y_top - 1) * SIZE_X + pixel_x);
-            is_checkered_boxed &=
-              colours.0 == mandelbrot_set_colour[pixel_top] &&
-                colours.1 == mandelbrot_set_colour[pixel_top + 1] &&
-                colours.2 == mandelbrot_set_colour[pixel_top + 2];
+            let pixel_top = (pixel_y_top - 1) * width + pixel_x;
+            is_checkered_boxed &= cluster_iterations == buffer.iterations[pixel_top];
           }
         }
 
-        if cluster_y != (HALF_Y / CLUSTER_SIZE) - 1 {
+        if cluster_y != clusters_y - 1 {
           for pixel_x in pixel_x_left..pixel_x_right {
-            let pixel_bottom = 3 * ((pixel_y_bottom + 1) * SIZE_X + pixel_x);
-            is_checkered_boxed &=
-              colours.0 == mandelbrot_set_colour[pixel_bottom] &&
-                colours.1 == mandelbrot_set_colour[pixel_bottom + 1] &&
-                colours.2 == mandelbrot_set_colour[pixel_bottom + 2];
+            let pixel_bottom = (pixel_y_bottom + 1) * width + pixel_x;
+            is_checkered_boxed &= cluster_iterations == buffer.iterations[pixel_bottom];
           }
         }
 
         if cluster_x != 0 {
           for pixel_y in pixel_y_top..pixel_y_bottom {
-            let pixel_left = 3 * (pixel_y * SIZE_X + pixel_x_left - 1);
-            is_checkered_boxed &=
-              colours.0 == mandelbrot_set_colour[pixel_left] &&
-                colours.1 == mandelbrot_set_colour[pixel_left + 1] &&
-                colours.2 == mandelbrot_set_colour[pixel_left + 2];
+            let pixel_left = pixel_y * width + pixel_x_left - 1;
+            is_checkered_boxed &= cluster_iterations == buffer.iterations[pixel_left];
           }
         }
 
-        if cluster_x != (SIZE_X / CLUSTER_SIZE) - 1 {
+        if cluster_x != clusters_x - 1 {
           for pixel_y in pixel_y_top..pixel_y_bottom {
-            let pixel_right = 3 * (pixel_y * SIZE_X + pixel_x_right + 1);
-            is_checkered_boxed &=
-              colours.0 == mandelbrot_set_colour[pixel_right] &&
-                colours.1 == mandelbrot_set_colour[pixel_right + 1] &&
-                colours.2 == mandelbrot_set_colour[pixel_right + 2];
+            let pixel_right = pixel_y * width + pixel_x_right + 1;
+            is_checkered_boxed &= cluster_iterations == buffer.iterations[pixel_right];
           }
         }
 
         if is_checkered_boxed {
+          buffer.set(pixel_x_left, pixel_y_top, &cluster_result);
+
           for pixel_y in pixel_y_top..(pixel_y_bottom + 1) {
             for pixel_x in pixel_x_left..(pixel_x_right + 1) {
-              let pixel = 3 * (pixel_y * SIZE_X + pixel_x);
-              mandelbrot_set_colour[pixel] = colours.0;
-              mandelbrot_set_colour[pixel + 1] = colours.1;
-              mandelbrot_set_colour[pixel + 2] = colours.2;
+              buffer.copy((pixel_x_left, pixel_y_top), (pixel_x, pixel_y));
             }
           }
 
@@ -439,42 +384,40 @@ pub fn gms_cluster_checkered(algo: EscapeAlgorithm) -> Vec<u8> {
         }
       }
 
-      compute_cluster(algo, &mut mandelbrot_set_colour, pixel_y_top, pixel_y_bottom, pixel_x_left, pixel_x_right)
+      compute_cluster(viewport, params, formula, kernel, &mut buffer, pixel_y_top, pixel_y_bottom, pixel_x_left, pixel_x_right)
     }
   }
 
-  for column in 0..HALF_Y {
-    let column_index_start = COLOUR_ROW_SIZE * (HALF_Y - column - 1);
-    let column_index_end = COLOUR_ROW_SIZE * (HALF_Y - column);
-
-    mandelbrot_set_colour.extend_from_within(column_index_start..column_index_end);
-  }
+  mirror_half(&mut buffer, viewport);
 
-  return mandelbrot_set_colour;
+  return buffer;
 }
 
 // Extremely inefficient (= gms time...)
-pub fn gms_parallel(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE];
+pub fn gms_parallel<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = new_buffer(viewport, params, formula);
 
   let (tx, px) = std::sync::mpsc::channel();
 
   {
-    const X_SLICE: usize = SIZE_X / THREADS;
+    let x_slice = viewport.width.div_ceil(THREADS);
 
     let mut threads: Vec<JoinHandle<()>> = vec!();
 
     for thread_index in 0..THREADS {
       let tx = tx.clone();
+      let viewport = *viewport;
+      let params = params.clone();
+      let formula = formula.clone();
 
       let thread = thread::spawn(move || {
-        for py in 0..HALF_Y {
-          let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+        for py in 0..viewport.computed_rows() {
+          let y0 = viewport.y0(py);
 
-          for px in (X_SLICE * thread_index)..(X_SLICE * (thread_index + 1)) {
-            let x0 = SCALE_X * (px as f32) + GRID_X.0;
-            let iterations = algo(y0, x0);
-            tx.send((px, py, iterations)).expect("Failed to send iterations");
+          for px in (x_slice * thread_index)..(x_slice * (thread_index + 1)).min(viewport.width) {
+            let x0 = viewport.x0(px);
+            let result = kernel.escape(y0, x0, &formula, &params);
+            tx.send((px, py, result)).expect("Failed to send iterations");
           }
         }
       });
@@ -487,124 +430,114 @@ pub fn gms_parallel(algo: EscapeAlgorithm) -> Vec<u8> {
     }
   }
 
-  for _ in 0..(HALF_Y * SIZE_X) {
-    let (x, y, iterations) = px.recv().expect("Failed to receive iterations");
-    colour_half(&mut set_colour, x, y, iterations);
+  for _ in 0..(viewport.computed_rows() * viewport.width) {
+    let (x, y, result) = px.recv().expect("Failed to receive iterations");
+    buffer.set(x, y, &result);
   }
 
-  return set_colour;
+  mirror_half(&mut buffer, viewport);
+
+  return buffer;
 }
 
 // Somewhat inefficient (~ 3/5 gms time)
-pub fn gms_parallel2(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut set_colour = (0..HALF_Y)
-      .map(|index|
+pub fn gms_parallel2<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = (0..viewport.computed_rows())
+      .map(|index| {
+          let viewport = *viewport;
+          let params = params.clone();
+          let formula = formula.clone();
+
           thread::spawn(move || {
-            let mut colours = [0; COLOUR_ROW_SIZE];
+            let mut row = new_rows(&viewport, &params, &formula, 1);
             let py = index;
-            let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+            let y0 = viewport.y0(py);
 
-            for px in 0..SIZE_X {
-              let x0 = SCALE_X * (px as f32) + GRID_X.0;
-              let iterations = algo(y0, x0);
-              colour_set(&mut colours, px, 0, iterations);
+            for px in 0..viewport.width {
+              let x0 = viewport.x0(px);
+              let result = kernel.escape(y0, x0, &formula, &params);
+              row.set(px, 0, &result);
             }
-            return (index, colours);
+            return (index, row);
           })
-      )
-      .fold(vec![0; COLOUR_ARRAY_SIZE], |mut acc, thread| {
+      })
+      .fold(new_buffer(viewport, params, formula), |mut acc, thread| {
         let result = thread.join().unwrap();
-        let offset: usize = result.0 * COLOUR_ROW_SIZE;
-
-        for i in 0..COLOUR_ROW_SIZE {
-          acc[offset + i] = result.1[i];
-        }
+        acc.copy_row(result.0, &result.1);
 
         return acc;
       });
 
-  for column in 0..(HALF_Y) {
-    let column_index = COLOUR_ROW_SIZE * column;
-    let opposite_column_index = COLOUR_ROW_SIZE * (SIZE_Y - column - 1);
-    for row in 0..COLOUR_ROW_SIZE {
-      set_colour[opposite_column_index + row] = set_colour[column_index + row]
-    }
-  }
+  mirror_half(&mut buffer, viewport);
 
-  return set_colour;
+  return buffer;
 }
 
 // Mildly inefficient (~ gms_half / 2.5)
-pub fn gms_parallel3(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut thread_enum = (0..HALF_Y)
-    .map(|index|
+pub fn gms_parallel3<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut thread_enum = (0..viewport.computed_rows())
+    .map(|index| {
+      let viewport = *viewport;
+      let params = params.clone();
+      let formula = formula.clone();
+
       thread::spawn(move || {
-        let mut colours = [0; COLOUR_ROW_SIZE];
+        let mut row = new_rows(&viewport, &params, &formula, 1);
         let py = index;
-        let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+        let y0 = viewport.y0(py);
 
-        for px in 0..SIZE_X {
-          let x0 = SCALE_X * (px as f32) + GRID_X.0;
-          let iterations = algo(y0, x0);
-          colour_row(&mut colours, px, iterations);
+        for px in 0..viewport.width {
+          let x0 = viewport.x0(px);
+          let result = kernel.escape(y0, x0, &formula, &params);
+          row.set(px, 0, &result);
         }
-        return (index, colours);
+        return (index, row);
       })
-    );
+    });
 
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE];
-  let mut active_threads = VecDeque::with_capacity(THREADS as usize);
+  let mut buffer = new_buffer(viewport, params, formula);
+  let mut active_threads = VecDeque::with_capacity(THREADS);
 
   {
     for _ in 0..(THREADS + 1) {
-      let new_thread = thread_enum.next();
-      if new_thread.is_some() {
-        active_threads.push_back(new_thread.unwrap());
+      if let Some(new_thread) = thread_enum.next() {
+        active_threads.push_back(new_thread);
       }
     }
 
     while !active_threads.is_empty() {
       let result = active_threads.pop_front().unwrap().join().unwrap();
-      let offset: usize = result.0 * COLOUR_ROW_SIZE;
-
-      for i in 0..COLOUR_ROW_SIZE {
-        set_colour[offset + i] = result.1[i];
-      }
+      buffer.copy_row(result.0, &result.1);
 
-      let new_thread = thread_enum.next();
-      if new_thread.is_some() {
-        active_threads.push_back(new_thread.unwrap());
+      if let Some(new_thread) = thread_enum.next() {
+        active_threads.push_back(new_thread);
       }
     }
   }
 
-  for column in 0..(HALF_Y) {
-    let column_index = COLOUR_ROW_SIZE * column;
-    let opposite_column_index = COLOUR_ROW_SIZE * (SIZE_Y - column - 1);
-    for row in 0..COLOUR_ROW_SIZE {
-      set_colour[opposite_column_index + row] = set_colour[column_index + row]
-    }
-  }
+  mirror_half(&mut buffer, viewport);
 
-  return set_colour;
+  return buffer;
 }
 
-pub fn gms_parallel_scoped(algo: EscapeAlgorithm) -> Vec<u8> {
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE / 2];
+pub fn gms_parallel_scoped<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = new_buffer(viewport, params, formula);
 
   thread::scope(|scope| {
-    let threads = set_colour
-      .chunks_mut(COLOUR_ROW_SIZE)
+    let threads = buffer
+      .rows_mut()
+      .into_iter()
+      .take(viewport.computed_rows())
       .enumerate()
       .rev()
-      .map(|(py, chunk)|
+      .map(|(py, mut row)|
         scope.spawn(move || {
-          let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+          let y0 = viewport.y0(py);
 
-          for px in 0..SIZE_X {
-            let x0 = SCALE_X * (px as f32) + GRID_X.0;
-            let iterations = algo(y0, x0);
-            colour_row(chunk, px, iterations);
+          for px in 0..viewport.width {
+            let x0 = viewport.x0(px);
+            let result = kernel.escape(y0, x0, formula, params);
+            row.set(px, &result);
           }
         })
       );
@@ -612,38 +545,34 @@ pub fn gms_parallel_scoped(algo: EscapeAlgorithm) -> Vec<u8> {
     for _ in threads {}
   });
 
-  for column in 0..HALF_Y {
-    let column_index_start = COLOUR_ROW_SIZE * (HALF_Y - column - 1);
-    let column_index_end = COLOUR_ROW_SIZE * (HALF_Y - column);
+  mirror_half(&mut buffer, viewport);
 
-    set_colour.extend_from_within(column_index_start..column_index_end);
-  }
-
-  return set_colour;
+  return buffer;
 }
 
-pub fn gms_parallel_scoped_pixel(algo: EscapeAlgorithm) -> Vec<u8> {
+pub fn gms_parallel_scoped_pixel<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
   const CHUNKS_PER_ROW: usize = 4;
-  const CHUNK_SIZE: usize = COLOUR_ROW_SIZE / CHUNKS_PER_ROW;
-  const CHUNK_PIXELS: usize = CHUNK_SIZE / 3;
+  let chunk_pixels = viewport.width.div_ceil(CHUNKS_PER_ROW);
 
-  println!("{} KB", CHUNK_SIZE / 1024);
+  println!("{} KB", std::mem::size_of::<u32>() * chunk_pixels / 1024);
 
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE / 2];
+  let mut buffer = new_buffer(viewport, params, formula);
   thread::scope(|scope| {
-    let mut threads = set_colour
-      .chunks_mut(CHUNK_SIZE)
+    let threads = buffer
+      .rows_mut()
+      .into_iter()
+      .take(viewport.computed_rows())
       .enumerate()
-      .map(|(index, chunk)|
+      .flat_map(|(py, row)| row.chunks(chunk_pixels).into_iter().enumerate().map(move |(index, chunk)| (py, index, chunk)))
+      .map(|(py, index, mut chunk)|
         scope.spawn(move || {
-          let py = index / CHUNKS_PER_ROW;
-          let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
-
-          let px_offset = CHUNK_PIXELS * (index % CHUNKS_PER_ROW);
-          for px in 0..CHUNK_PIXELS {
-            let x0 = SCALE_X * (px_offset + px) as f32 + GRID_X.0;
-            let iterations = algo(y0, x0);
-            colour_row(chunk, px, iterations);
+          let y0 = viewport.y0(py);
+
+          let px_offset = chunk_pixels * index;
+          for px in 0..chunk.len() {
+            let x0 = viewport.x0(px_offset + px);
+            let result = kernel.escape(y0, x0, formula, params);
+            chunk.set(px, &result);
           }
         })
       );
@@ -651,34 +580,29 @@ pub fn gms_parallel_scoped_pixel(algo: EscapeAlgorithm) -> Vec<u8> {
     for _ in threads {}
   });
 
-  for column in 0..HALF_Y {
-    let column_index_start = COLOUR_ROW_SIZE * (HALF_Y - column - 1);
-    let column_index_end = COLOUR_ROW_SIZE * (HALF_Y - column);
-
-    set_colour.extend_from_within(column_index_start..column_index_end);
-  }
+  mirror_half(&mut buffer, viewport);
 
-  return set_colour;
+  return buffer;
 }
 
-pub fn gms_parallel_cluster(algo: EscapeAlgorithm) -> Vec<u8> {
-  const CLUSTER_SIZE: usize = 1 << 3;
-
-  let mut set_colour = vec![0; COLOUR_ARRAY_SIZE / 2];
+pub fn gms_parallel_cluster<T: Real, F: Formula, K: Kernel>(viewport: &Viewport<T>, params: &Parameters, formula: &F, kernel: K) -> IterationBuffer {
+  let mut buffer = new_buffer(viewport, params, formula);
 
   thread::scope(|scope| {
-    let threads = set_colour
-      .chunks_mut(COLOUR_ROW_SIZE)
+    let threads = buffer
+      .rows_mut()
+      .into_iter()
+      .take(viewport.computed_rows())
       .enumerate()
       .rev()
-      .map(|(py, chunk)|
+      .map(|(py, mut row)|
         scope.spawn(move || {
-          let y0 = SCALE_Y * (py as f32) + GRID_Y.0;
+          let y0 = viewport.y0(py);
 
-          for px in 0..SIZE_X {
-            let x0 = SCALE_X * (px as f32) + GRID_X.0;
-            let iterations = algo(y0, x0);
-            colour_row(chunk, px, iterations);
+          for px in 0..viewport.width {
+            let x0 = viewport.x0(px);
+            let result = kernel.escape(y0, x0, formula, params);
+            row.set(px, &result);
           }
         })
       );
@@ -686,12 +610,139 @@ pub fn gms_parallel_cluster(algo: EscapeAlgorithm) -> Vec<u8> {
     for _ in threads {}
   });
 
-  for column in 0..HALF_Y {
-    let column_index_start = COLOUR_ROW_SIZE * (HALF_Y - column - 1);
-    let column_index_end = COLOUR_ROW_SIZE * (HALF_Y - column);
+  mirror_half(&mut buffer, viewport);
 
-    set_colour.extend_from_within(column_index_start..column_index_end);
+  return buffer;
+}
+
+// Deep zooms: the viewport holds f64 offsets from the arbitrary precision centre, pixels iterate as deltas from a
+// reference orbit and glitched pixels are recomputed against a new reference chosen from among them. Resolved
+// pixels go straight into the buffer, the rest only keep how badly they glitched
+pub fn gms_perturbation(viewport: &Viewport<f64>, params: &Parameters, perturbation: &Perturbation) -> (IterationBuffer, PerturbationStats) {
+  const MAX_REFERENCES: usize = 64;
+  // Glitch severity of a pixel that needs no further reference
+  const RESOLVED: f32 = f32::INFINITY;
+
+  let width = viewport.width;
+  let precision = perturbation.centre.0.precision();
+
+  let mut buffer = new_buffer(viewport, params, &Mandelbrot { bailout: params.bailout });
+  // Every pixel starts out pending, the same as a glitch
+  let mut severities = vec![1.0f32; width * viewport.height];
+  let mut reference = (0.0, 0.0);
+  let mut stats = PerturbationStats::default();
+
+  // The image corners and edge midpoints bound how far any pixel strays from a reference
+  let probe_x = [viewport.x0(0), viewport.x0(width / 2), viewport.x0(width - 1)];
+  let probe_y = [viewport.y0(0), viewport.y0(viewport.height / 2), viewport.y0(viewport.height - 1)];
+
+  for _ in 0..MAX_REFERENCES {
+    let reference_centre = (
+      perturbation.centre.0.add(&BigFixed::from_f64(reference.0, precision)),
+      perturbation.centre.1.add(&BigFixed::from_f64(reference.1, precision))
+    );
+    let orbit = ReferenceOrbit::compute(&reference_centre, params);
+    let orbit = &orbit;
+
+    let approximation = if perturbation.series {
+      let probes: Vec<(f64, f64)> = probe_y.iter()
+        .flat_map(|y| probe_x.iter().map(move |x| (x - reference.0, y - reference.1)))
+        .filter(|probe| *probe != (0.0, 0.0))
+        .collect();
+      SeriesApproximation::compute(orbit, &probes, params)
+    } else {
+      SeriesApproximation::none()
+    };
+    let approximation = &approximation;
+
+    let pending = severities.iter().filter(|severity| **severity != RESOLVED).count();
+    stats.series_skipped.push(approximation.skipped);
+    stats.pixel_iterations_skipped += pending as u64 * approximation.skipped as u64;
+
+    thread::scope(|scope| {
+      let threads = buffer
+        .rows_mut()
+        .into_iter()
+        .zip(severities.chunks_mut(width))
+        .enumerate()
+        .rev()
+        .map(|(py, (mut row, severities))|
+          scope.spawn(move || {
+            let dy0 = viewport.y0(py) - reference.1;
+
+            for (px, severity) in severities.iter_mut().enumerate() {
+              if *severity == RESOLVED {
+                continue;
+              }
+
+              let dx0 = viewport.x0(px) - reference.0;
+              let delta = approximation.delta(dy0, dx0);
+              match orbit.escape_time_from(approximation.skipped, delta, dy0, dx0, params) {
+                PerturbedEscape::Resolved(result) => {
+                  row.set(px, &result);
+                  *severity = RESOLVED;
+                },
+                PerturbedEscape::Glitched(glitch) => *severity = glitch as f32,
+              }
+            }
+          })
+        );
+
+      for _ in threads {}
+    });
+
+    let worst_glitch = severities.iter()
+      .enumerate()
+      .filter(|(_, severity)| **severity != RESOLVED)
+      .min_by(|a, b| a.1.total_cmp(b.1));
+
+    match worst_glitch {
+      Some((index, _)) => reference = (viewport.x0(index % width), viewport.y0(index / width)),
+      None => break,
+    }
+  }
+
+  // Pixels still glitched once the references run out stay interior, better than a wrong escape time
+  return (buffer, stats);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const WIDTH: usize = 160;
+  const HEIGHT: usize = 100;
+
+  // Iteration counts of a perturbation render, with the iterations the series skipped for each reference
+  fn perturbation(centre: (&str, &str), zoom: f64, max_iterations: u32, series: bool) -> (Vec<u32>, Vec<u32>) {
+    let limbs = BigFixed::limbs_for_zoom(zoom, HEIGHT);
+    let centre = (BigFixed::parse(centre.0, limbs).unwrap(), BigFixed::parse(centre.1, limbs).unwrap());
+    let viewport = Viewport::from_centre((0.0, 0.0), zoom, WIDTH, HEIGHT);
+    let params = Parameters::new(max_iterations);
+    let (buffer, stats) = gms_perturbation(&viewport, &params, &Perturbation { centre, series });
+    return (buffer.iterations, stats.series_skipped);
+  }
+
+  // Pixels near the boundary pass any error in delta on for thousands of iterations, so even iterating every pixel
+  // from zero a few disagree with a double-double render. The series may add about as many again
+  fn assert_series_matches(centre: (&str, &str), zoom: f64, max_iterations: u32) -> Vec<u32> {
+    let (series, skipped) = perturbation(centre, zoom, max_iterations, true);
+    let (exact, _) = perturbation(centre, zoom, max_iterations, false);
+
+    let differ = series.iter().zip(&exact).filter(|(series, exact)| series != exact).count();
+    assert!(differ * 500 <= series.len(), "{} of {} pixels differ, series skipped {:?}", differ, series.len(), skipped);
+
+    return skipped;
+  }
+
+  #[test]
+  fn series_matches_iterating_every_pixel_at_a_shallow_zoom() {
+    assert_series_matches(("-1.25066", "0.02012"), 1e5, 5000);
   }
 
-  return set_colour;
-}
\ No newline at end of file
+  #[test]
+  fn series_matches_iterating_every_pixel_while_skipping() {
+    let skipped = assert_series_matches(("-0.7436438870371587", "0.1318259042053119"), 1e12, 5000);
+    assert!(skipped[0] > 100, "series skipped {:?}", skipped);
+  }
+}
@@ -1,3 +1,15 @@
+pub mod big_fixed;
+pub mod buddhabrot;
 pub mod colour;
+pub mod double_double;
 pub mod escape;
-pub mod mandelbrot;
\ No newline at end of file
+pub mod expression;
+pub mod formula;
+pub mod iteration_buffer;
+pub mod mandelbrot;
+pub mod newton;
+pub mod palette;
+pub mod parameters;
+pub mod perturbation;
+pub mod real;
+pub mod viewport;
\ No newline at end of file
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use crate::mandelbrot::formula::Formula;
+use crate::mandelbrot::real::Real;
+
+// Distance from a root within which an orbit counts as settled on it, unless the roots lie closer together
+const ROOT_TOLERANCE: f64 = 1e-3;
+// Roots found this close to one another, relative to their size, are one repeated root
+const ROOT_MERGE: f64 = 1e-4;
+// Rounds of Durand-Kerner to find the roots of a polynomial given by its coefficients
+const ROOT_ROUNDS: u32 = 1000;
+
+// Newton's method on a polynomial p, z - p(z) / p'(z). Orbits start at the pixel and settle on a root instead of
+// escaping, the kernel stops once one lands within the tolerance of a root
+#[derive(Clone, Debug)]
+pub struct Newton {
+    // Highest power first. Shared like an expression's program
+    coefficients: Arc<[(f64, f64)]>,
+    // Distinct roots, numbered in the order they were given or found
+    roots: Arc<[(f64, f64)]>,
+    // Squared distance from a root within which an orbit has settled on it
+    tolerance: f64,
+}
+
+impl Newton {
+    // The monic polynomial with these roots, repeating a root repeats the factor
+    pub fn from_roots(roots: &[(f64, f64)]) -> Result<Newton, String> {
+        if roots.len() < 2 {
+            return Err(String::from("needs at least 2 roots, a single root draws no boundaries"));
+        }
+
+        let mut coefficients = vec![(1.0, 0.0)];
+        for root in roots {
+            // Multiply by (z - root)
+            let mut product = coefficients.clone();
+            product.push((0.0, 0.0));
+            for (index, coefficient) in coefficients.iter().enumerate() {
+                product[index + 1] = sub(product[index + 1], mul(*coefficient, *root));
+            }
+            coefficients = product;
+        }
+
+        return Ok(Newton::new(coefficients, roots));
+    }
+
+    pub fn from_coefficients(coefficients: &[(f64, f64)]) -> Result<Newton, String> {
+        let leading = coefficients.iter().position(|coefficient| *coefficient != (0.0, 0.0)).unwrap_or(coefficients.len());
+        let coefficients = &coefficients[leading..];
+        if coefficients.len() < 3 {
+            return Err(String::from("needs a polynomial of degree 2 or more, give its coefficients from the highest power down"));
+        }
+
+        let roots = durand_kerner(coefficients);
+        return Ok(Newton::new(coefficients.to_vec(), &roots));
+    }
+
+    fn new(coefficients: Vec<(f64, f64)>, roots: &[(f64, f64)]) -> Newton {
+        let mut distinct: Vec<(f64, f64)> = vec!();
+        for root in roots {
+            let close = |other: &(f64, f64)| distance(*root, *other) < ROOT_MERGE * (1.0 + modulus(*root));
+            if !distinct.iter().any(close) {
+                distinct.push(*root);
+            }
+        }
+
+        // A quarter of the closest spacing keeps every root's neighbourhood apart
+        let mut radius = ROOT_TOLERANCE;
+        for (index, root) in distinct.iter().enumerate() {
+            for other in &distinct[index + 1..] {
+                radius = radius.min(distance(*root, *other) / 4.0);
+            }
+        }
+
+        Newton {
+            coefficients: coefficients.into(),
+            roots: distinct.into(),
+            tolerance: radius * radius,
+        }
+    }
+
+    pub fn roots(&self) -> &[(f64, f64)] {
+        &self.roots
+    }
+}
+
+impl Formula for Newton {
+    // p and p' together by Horner's rule, p' taking in p before p takes in the next coefficient
+    #[inline(always)]
+    fn step<T: Real>(&self, x: T, y: T, _x2: T, _y2: T, _c: (T, T)) -> (T, T) {
+        let zero = T::from_f64(0.0);
+        let (mut px, mut py) = (zero, zero);
+        let (mut dx, mut dy) = (zero, zero);
+
+        for &(re, im) in self.coefficients.iter() {
+            (dx, dy) = (dx * x - dy * y + px, dx * y + dy * x + py);
+            (px, py) = (px * x - py * y + T::from_f64(re), px * y + py * x + T::from_f64(im));
+        }
+
+        let norm = dx * dx + dy * dy;
+        (x - (px * dx + py * dy) / norm, y - (py * dx - px * dy) / norm)
+    }
+
+    // Orbits never escape, only a vanishing p' can throw one off to infinity or NaN
+    fn escape_radius(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    // Conjugate and opposite pixels settle on different roots, so no part of the image is a copy of another
+    fn is_mirrored(&self) -> bool {
+        false
+    }
+
+    fn root<T: Real>(&self, x: T, y: T) -> Option<u32> {
+        let z = (x.to_f64(), y.to_f64());
+        let squared = |root: &(f64, f64)| (z.0 - root.0) * (z.0 - root.0) + (z.1 - root.1) * (z.1 - root.1);
+
+        self.roots.iter().position(|root| squared(root) < self.tolerance).map(|index| index as u32)
+    }
+
+    #[inline(always)]
+    fn start<T: Real>(&self, y0: T, x0: T) -> ((T, T), (T, T)) {
+        ((x0, y0), (x0, y0))
+    }
+}
+
+// All the roots at once, each moved by p(z) / the product of its differences from the others. Repeated roots come
+// out as a tight cluster, merged when the Newton formula is built
+fn durand_kerner(coefficients: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let leading = coefficients[0];
+    let monic: Vec<(f64, f64)> = coefficients.iter().map(|coefficient| div(*coefficient, leading)).collect();
+    let degree = monic.len() - 1;
+
+    // Every root lies within 1 + max |a_k| of the origin, start spread round a circle of that radius
+    let bound = 1.0 + monic[1..].iter().map(|coefficient| modulus(*coefficient)).fold(0.0, f64::max);
+    let mut roots: Vec<(f64, f64)> = (0..degree)
+        .map(|index| {
+            let angle = std::f64::consts::TAU * index as f64 / degree as f64 + 0.4;
+            (bound * angle.cos(), bound * angle.sin())
+        })
+        .collect();
+
+    for _ in 0..ROOT_ROUNDS {
+        let mut moved: f64 = 0.0;
+
+        for index in 0..degree {
+            let root = roots[index];
+            let value = monic.iter().fold((0.0, 0.0), |value, coefficient| add(mul(value, root), *coefficient));
+            let differences = roots.iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .fold((1.0, 0.0), |product, (_, other)| mul(product, sub(root, *other)));
+
+            let step = div(value, differences);
+            if step.0.is_finite() && step.1.is_finite() {
+                roots[index] = sub(root, step);
+                moved = moved.max(modulus(step) / (1.0 + modulus(root)));
+            }
+        }
+
+        if moved < 1e-15 {
+            break;
+        }
+    }
+
+    return roots;
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let norm = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / norm, (a.1 * b.0 - a.0 * b.1) / norm)
+}
+
+fn modulus(a: (f64, f64)) -> f64 {
+    a.0.hypot(a.1)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    modulus(sub(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mandelbrot::escape::{self, EscapeStatus};
+    use crate::mandelbrot::parameters::Parameters;
+
+    fn real(coefficients: &[f64]) -> Vec<(f64, f64)> {
+        coefficients.iter().map(|coefficient| (*coefficient, 0.0)).collect()
+    }
+
+    fn assert_roots(found: &[(f64, f64)], expected: &[(f64, f64)], tolerance: f64) {
+        assert_eq!(found.len(), expected.len(), "found {:?}", found);
+        for root in expected {
+            assert!(found.iter().any(|other| distance(*root, *other) < tolerance), "{:?} missing from {:?}", root, found);
+        }
+    }
+
+    #[test]
+    fn finds_the_cube_roots_of_unity() {
+        let newton = Newton::from_coefficients(&real(&[1.0, 0.0, 0.0, -1.0])).unwrap();
+        let half = 3f64.sqrt() / 2.0;
+
+        assert_roots(newton.roots(), &[(1.0, 0.0), (-0.5, half), (-0.5, -half)], 1e-12);
+    }
+
+    #[test]
+    fn merges_a_repeated_root() {
+        // (z - 1)^2 (z + 1), Durand-Kerner only closes in on the double root to about the square root of f64's error
+        let newton = Newton::from_coefficients(&real(&[1.0, -1.0, -1.0, 1.0])).unwrap();
+        assert_roots(newton.roots(), &[(1.0, 0.0), (-1.0, 0.0)], 1e-6);
+
+        let newton = Newton::from_roots(&[(1.0, 0.0), (1.0, 0.0), (-1.0, 0.0)]).unwrap();
+        assert_eq!(newton.roots(), [(1.0, 0.0), (-1.0, 0.0)]);
+    }
+
+    #[test]
+    fn pixels_near_a_root_settle_on_it() {
+        let newton = Newton::from_coefficients(&real(&[1.0, 0.0, 0.0, -1.0])).unwrap();
+        let params = Parameters::new(100);
+
+        for (index, root) in newton.roots().iter().enumerate() {
+            let result = escape::newton(root.1 + 0.05, root.0 - 0.05, &newton, &params);
+
+            assert_eq!(result.status, EscapeStatus::Converged);
+            assert_eq!(result.root, Some(index as u32), "started near {:?}", root);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+  // Straight lines between stops in linear light
+  Rgb,
+  // Perceptually even blends, avoids the muddy midpoints of RGB
+  OkLab,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repeat {
+  // Hold the end colours past either end of the gradient
+  Clamp,
+  // Start again from the first stop, blending the last stop back into it
+  Wrap,
+  // Run back down the gradient every other pass
+  Mirror,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Stop {
+  position: f64,
+  // Linear RGB, 0..1
+  colour: [f64; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct Palette {
+  stops: Vec<Stop>,
+  pub interpolation: Interpolation,
+  // Fraction of the gradient to shift every lookup by
+  pub offset: f64,
+  // Input value covered by one pass through the gradient, e.g. smooth iterations
+  pub scale: f64,
+  pub repeat: Repeat,
+}
+
+impl Palette {
+  // Stops are (position in 0..1, sRGB colour), two stops at the same position make a hard edge
+  pub fn new(stops: &[(f64, (u8, u8, u8))]) -> Result<Palette, String> {
+    if stops.is_empty() {
+      return Err(String::from("a palette needs at least one colour stop"));
+    }
+
+    if let Some((position, _)) = stops.iter().find(|(position, _)| !(0.0..=1.0).contains(position)) {
+      return Err(format!("colour stop position {} is outside 0..1", position));
+    }
+
+    let mut stops: Vec<Stop> = stops.iter()
+      .map(|(position, (r, g, b))| Stop {
+        position: *position,
+        colour: [srgb_to_linear(*r), srgb_to_linear(*g), srgb_to_linear(*b)],
+      })
+      .collect();
+    stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+    Ok(Palette {
+      stops,
+      interpolation: Interpolation::OkLab,
+      offset: 0.0,
+      scale: 64.0,
+      repeat: Repeat::Wrap,
+    })
+  }
+
+  pub fn colour(&self, value: f64) -> (u8, u8, u8) {
+    self.colour_at(value / self.scale)
+  }
+
+  // Look up a position along the gradient directly, offset and repeat still apply
+  pub fn colour_at(&self, t: f64) -> (u8, u8, u8) {
+    let t = t + self.offset;
+    let t = match self.repeat {
+      Repeat::Clamp => t.clamp(0.0, 1.0),
+      Repeat::Wrap => t.rem_euclid(1.0),
+      Repeat::Mirror => 1.0 - (t.rem_euclid(2.0) - 1.0).abs(),
+    };
+
+    let [r, g, b] = self.sample(t);
+    (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+  }
+
+  fn sample(&self, t: f64) -> [f64; 3] {
+    let first = &self.stops[0];
+    let last = &self.stops[self.stops.len() - 1];
+    let next = self.stops.partition_point(|stop| stop.position <= t);
+
+    // Outside the outermost stops a wrapping gradient blends from the last stop round to the first
+    let (from, from_position, to, to_position) = if next == 0 {
+      if self.repeat != Repeat::Wrap {
+        return first.colour;
+      }
+      (last, last.position - 1.0, first, first.position)
+    } else if next == self.stops.len() {
+      if self.repeat != Repeat::Wrap {
+        return last.colour;
+      }
+      (last, last.position, first, first.position + 1.0)
+    } else {
+      let from = &self.stops[next - 1];
+      let to = &self.stops[next];
+      (from, from.position, to, to.position)
+    };
+
+    let span = to_position - from_position;
+    let fraction = if span > 0.0 { (t - from_position) / span } else { 0.0 };
+
+    match self.interpolation {
+      Interpolation::Rgb => lerp(from.colour, to.colour, fraction),
+      Interpolation::OkLab => oklab_to_linear(lerp(linear_to_oklab(from.colour), linear_to_oklab(to.colour), fraction)),
+    }
+  }
+}
+
+// Deep blue through white and gold to black, the usual escape time gradient
+impl Default for Palette {
+  fn default() -> Palette {
+    Palette::new(&[
+      (0.0, (0, 7, 100)),
+      (0.16, (32, 107, 203)),
+      (0.42, (237, 255, 255)),
+      (0.6425, (255, 170, 0)),
+      (0.8575, (0, 2, 0)),
+    ]).expect("default palette stops are valid")
+  }
+}
+
+fn lerp(a: [f64; 3], b: [f64; 3], t: f64) -> [f64; 3] {
+  [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+fn srgb_to_linear(channel: u8) -> f64 {
+  let c = channel as f64 / 255.0;
+  if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> u8 {
+  let c = c.clamp(0.0, 1.0);
+  let encoded = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+  (255.0 * encoded).round() as u8
+}
+
+// Björn Ottosson's OKLab, from and to linear sRGB
+fn linear_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+  let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+  let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+  let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+  [
+    0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+    1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+    0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+  ]
+}
+
+fn oklab_to_linear([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+  let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+  let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+  let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+  [
+    4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+    -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+    -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+  ]
+}
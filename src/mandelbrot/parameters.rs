@@ -0,0 +1,60 @@
+use crate::mandelbrot::colour::{Colouring, Interior, Light};
+use crate::mandelbrot::escape::{DEFAULT_BAILOUT, DEFAULT_MAX_ITERATIONS, DEFAULT_STRIPE_DENSITY, Trap};
+use crate::mandelbrot::iteration_buffer::Channels;
+use crate::mandelbrot::palette::Palette;
+
+// Per-render settings shared by the generators, escape kernels and colouring
+#[derive(Clone, Debug)]
+pub struct Parameters {
+  pub max_iterations: u32,
+  // Escape radius, smooth colouring is more accurate the larger it is. Formulas may raise it to their own
+  pub bailout: f64,
+  // Shape the trap kernel measures orbits against
+  pub trap: Trap,
+  // Stripes per turn for the average kernel's stripe average
+  pub stripe_density: f64,
+  pub colouring: Colouring,
+  pub interior: Interior,
+  // Normal-map shading of the exterior
+  pub light: Option<Light>,
+  // Gradient for smooth colouring
+  pub palette: Palette,
+  // Data to keep even when the colouring ignores it, e.g. to save it
+  pub channels: Channels,
+}
+
+impl Parameters {
+  #[cfg(test)]
+  pub fn new(max_iterations: u32) -> Parameters {
+    Parameters { max_iterations, ..Parameters::default() }
+  }
+
+  pub fn bailout_squared(&self) -> f64 {
+    self.bailout * self.bailout
+  }
+
+  // Everything the generators have to keep per pixel
+  pub fn channels(&self) -> Channels {
+    let lighting = Channels { normal: self.light.is_some(), ..Channels::default() };
+    // Orbits that miss the stalks are coloured by their normalised iteration count
+    let stalks = Channels { smooth: matches!(self.trap, Trap::Stalks(_)) && self.colouring == Colouring::Trap, ..Channels::default() };
+
+    self.channels.union(self.colouring.channels()).union(self.interior.channels()).union(lighting).union(stalks)
+  }
+}
+
+impl Default for Parameters {
+  fn default() -> Parameters {
+    Parameters {
+      max_iterations: DEFAULT_MAX_ITERATIONS,
+      bailout: DEFAULT_BAILOUT,
+      trap: Trap::Point((0.0, 0.0)),
+      stripe_density: DEFAULT_STRIPE_DENSITY,
+      colouring: Colouring::Banded,
+      interior: Interior::Black,
+      light: None,
+      palette: Palette::default(),
+      channels: Channels::default(),
+    }
+  }
+}
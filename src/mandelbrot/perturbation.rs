@@ -0,0 +1,204 @@
+use crate::mandelbrot::big_fixed::BigFixed;
+use crate::mandelbrot::escape::{EscapeResult, EscapeStatus};
+use crate::mandelbrot::parameters::Parameters;
+
+// Pauldelbrot's criterion, squared: a pixel whose |z| falls below this fraction of the reference's |Z| has lost precision
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+// The series stops once a probe's estimated delta is further than this fraction of its exact delta
+const SERIES_TOLERANCE: f64 = 1e-6;
+// Or once its last term could reach this fraction of its first. Orbits near the boundary carry any error in delta
+// on for thousands of iterations, so it is kept close to the error f64 already makes
+const SERIES_TRUNCATION: f64 = 1e-10;
+// Iterations the series backs off from where it stopped, each a factor of about |2 Z_n| less error. Pixels between
+// the probes can also escape a little before any of them
+const SERIES_MARGIN: usize = 4;
+
+pub struct ReferenceOrbit {
+  // Z_n rounded to f64, z_0 = 0 included
+  points: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PerturbedEscape {
+  Resolved(EscapeResult<f64>),
+  // How far below the reference's magnitude the pixel fell, smaller is a worse glitch
+  Glitched(f64),
+}
+
+impl ReferenceOrbit {
+  pub fn compute(centre: &(BigFixed, BigFixed), params: &Parameters) -> ReferenceOrbit {
+    let max_iterations = params.max_iterations;
+    let (x0, y0) = centre;
+    let mut points = Vec::with_capacity(max_iterations as usize + 1);
+
+    let mut x = BigFixed::zero(x0.precision());
+    let mut y = x.clone();
+    points.push((0.0, 0.0));
+
+    for _ in 0..max_iterations {
+      let x2 = x.mul(&x);
+      let y2 = y.mul(&y);
+      y = x.mul(&y).mul_small(2).add(y0);
+      x = x2.sub(&y2).add(x0);
+
+      let point = (x.to_f64(), y.to_f64());
+      points.push(point);
+
+      if norm(point) > params.bailout_squared() {
+        break;
+      }
+    }
+
+    ReferenceOrbit { points }
+  }
+
+  // Iterate delta_{n+1} = 2 Z_n delta_n + delta_n^2 + delta_c for a pixel offset (dx0, dy0) from the reference,
+  // resuming at iteration start with delta already known, e.g. from a series approximation
+  pub fn escape_time_from(&self, start: u32, delta: (f64, f64), dy0: f64, dx0: f64, params: &Parameters) -> PerturbedEscape {
+    let max_iterations = params.max_iterations;
+    let bailout = params.bailout_squared();
+    let (mut dx, mut dy) = delta;
+    let last = self.points.len() - 1;
+
+    for iteration in (start as usize)..(max_iterations as usize) {
+      let (zx, zy) = self.points[iteration];
+      let x = zx + dx;
+      let y = zy + dy;
+      let magnitude = x * x + y * y;
+
+      if magnitude > bailout {
+        return PerturbedEscape::Resolved(resolved(iteration as u32, (x, y), EscapeStatus::Escaped));
+      }
+
+      let reference_magnitude = zx * zx + zy * zy;
+      if magnitude < GLITCH_TOLERANCE * reference_magnitude {
+        return PerturbedEscape::Glitched(magnitude / reference_magnitude);
+      }
+
+      // The reference escaped before this pixel did, so there is nothing left to perturb around
+      if iteration == last {
+        return PerturbedEscape::Glitched(1.0);
+      }
+
+      let next_dx = 2.0 * (zx * dx - zy * dy) + dx * dx - dy * dy + dx0;
+      dy = 2.0 * (zx * dy + zy * dx) + 2.0 * dx * dy + dy0;
+      dx = next_dx;
+    }
+
+    let last_z = add(self.points[max_iterations as usize], (dx, dy));
+    return PerturbedEscape::Resolved(resolved(max_iterations, last_z, EscapeStatus::Interior));
+  }
+}
+
+// Truncated series delta_n = A_n dc + B_n dc^2 + C_n dc^3 standing in for the iterations every pixel shares
+pub struct SeriesApproximation {
+  pub skipped: u32,
+  coefficients: [(f64, f64); 3],
+}
+
+impl SeriesApproximation {
+  pub fn none() -> SeriesApproximation {
+    SeriesApproximation { skipped: 0, coefficients: [(0.0, 0.0); 3] }
+  }
+
+  // Advance the coefficients alongside exact deltas for the probes, stopping before any probe disagrees with the
+  // series or the last term could grow past SERIES_TRUNCATION of the first anywhere in the image, then back off
+  // SERIES_MARGIN iterations. The probes alone only vouch for themselves, not the pixels between them
+  pub fn compute(orbit: &ReferenceOrbit, probes: &[(f64, f64)], params: &Parameters) -> SeriesApproximation {
+    // Coefficients giving delta_n, from delta_0 = 0
+    let mut history = vec![[(0.0, 0.0); 3]];
+    let mut deltas = vec![(0.0, 0.0); probes.len()];
+    let last = (orbit.points.len() - 1).min(params.max_iterations as usize);
+    // Largest |dc|, at the corners of the image
+    let radius_squared = probes.iter().map(|probe| norm(*probe)).fold(0.0, f64::max);
+
+    'terms: for iteration in 0..last {
+      let z = orbit.points[iteration];
+      let two_z = (2.0 * z.0, 2.0 * z.1);
+      let [a, b, c] = history[iteration];
+
+      let next = [
+        add(mul(two_z, a), (1.0, 0.0)),
+        add(mul(two_z, b), mul(a, a)),
+        add(mul(two_z, c), mul((2.0 * a.0, 2.0 * a.1), b)),
+      ];
+
+      let next_z = orbit.points[iteration + 1];
+      for (delta, probe) in deltas.iter_mut().zip(probes) {
+        *delta = add(add(mul(two_z, *delta), mul(*delta, *delta)), *probe);
+
+        let point = add(next_z, *delta);
+        if norm(point) > params.bailout_squared() {
+          break 'terms;
+        }
+      }
+
+      let candidate = SeriesApproximation { skipped: iteration as u32 + 1, coefficients: next };
+      let valid = deltas.iter().zip(probes).all(|(delta, probe)| {
+        let error = sub(candidate.delta(probe.1, probe.0), *delta);
+        norm(error) <= SERIES_TOLERANCE * SERIES_TOLERANCE * norm(*delta)
+      });
+
+      // |C| |dc|^3 against |A| |dc|, squared, the terms cut off are smaller again
+      let [a, _, c] = next;
+      let truncated = norm(c) * radius_squared * radius_squared <= SERIES_TRUNCATION * SERIES_TRUNCATION * norm(a);
+
+      if !(valid && truncated) {
+        break;
+      }
+      history.push(next);
+    }
+
+    let skipped = (history.len() - 1).saturating_sub(SERIES_MARGIN);
+    return SeriesApproximation { skipped: skipped as u32, coefficients: history[skipped] };
+  }
+
+  pub fn delta(&self, dy0: f64, dx0: f64) -> (f64, f64) {
+    let dc = (dx0, dy0);
+    let [a, b, c] = self.coefficients;
+
+    // Horner's rule: dc (A + dc (B + dc C))
+    mul(dc, add(a, mul(dc, add(b, mul(dc, c)))))
+  }
+}
+
+fn resolved(iterations: u32, z: (f64, f64), status: EscapeStatus) -> EscapeResult<f64> {
+  EscapeResult { iterations, z, magnitude: norm(z), derivative: None, status, period: None, cycle: None, trap: None, triangle: None, stripe: None, root: None }
+}
+
+#[inline(always)]
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+  (a.0 + b.0, a.1 + b.1)
+}
+
+#[inline(always)]
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+  (a.0 - b.0, a.1 - b.1)
+}
+
+#[inline(always)]
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+  (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+#[inline(always)]
+fn norm(a: (f64, f64)) -> f64 {
+  a.0 * a.0 + a.1 * a.1
+}
+
+#[derive(Debug, Default)]
+pub struct PerturbationStats {
+  // Iterations the series skipped for each reference in turn
+  pub series_skipped: Vec<u32>,
+  // Summed over every pixel iterated against each reference
+  pub pixel_iterations_skipped: u64,
+}
+
+// The Mandelbrot set about an arbitrary precision centre, with the viewport holding f64 offsets from it
+#[derive(Clone, Debug)]
+pub struct Perturbation {
+  pub centre: (BigFixed, BigFixed),
+  // Skip the iterations every pixel shares with a series approximation
+  pub series: bool,
+}
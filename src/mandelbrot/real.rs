@@ -0,0 +1,47 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+// Float type the escape kernels and viewport mapping are generic over
+pub trait Real:
+  Copy + Debug + PartialOrd + FromStr + Send + Sync + 'static +
+  Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+{
+  fn from_f64(value: f64) -> Self;
+  fn to_f64(self) -> f64;
+  fn abs(self) -> Self;
+}
+
+impl Real for f32 {
+  #[inline(always)]
+  fn from_f64(value: f64) -> f32 {
+    value as f32
+  }
+
+  #[inline(always)]
+  fn to_f64(self) -> f64 {
+    self as f64
+  }
+
+  #[inline(always)]
+  fn abs(self) -> f32 {
+    f32::abs(self)
+  }
+}
+
+impl Real for f64 {
+  #[inline(always)]
+  fn from_f64(value: f64) -> f64 {
+    value
+  }
+
+  #[inline(always)]
+  fn to_f64(self) -> f64 {
+    self
+  }
+
+  #[inline(always)]
+  fn abs(self) -> f64 {
+    f64::abs(self)
+  }
+}
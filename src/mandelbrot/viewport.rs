@@ -0,0 +1,175 @@
+use crate::mandelbrot::real::Real;
+
+pub const DEFAULT_GRID_X: (f64, f64) = (-2.0, 1.0);
+pub const DEFAULT_GRID_Y: (f64, f64) = (-1.0, 1.0);
+// Julia sets lie within |z| <= 2, centred on the origin
+pub const DEFAULT_JULIA_GRID_X: (f64, f64) = (-2.25, 2.25);
+pub const DEFAULT_JULIA_GRID_Y: (f64, f64) = (-1.5, 1.5);
+
+// Vertical extent of the complex plane shown at zoom 1
+const ZOOM_HEIGHT: f64 = DEFAULT_GRID_Y.1 - DEFAULT_GRID_Y.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aspect {
+  // Use the bounds as given, pixels may not be square
+  Stretch,
+  // Widen the short axis so the whole region is visible with square pixels
+  Fit,
+  // Narrow the long axis so the region fills the image with square pixels
+  Fill,
+}
+
+// How the bottom half of the image follows from the top half
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+  // Reflected across the real axis, as the Mandelbrot set and Julia sets of real c are
+  Mirror,
+  // Rotated half a turn about the origin, as Julia sets of even powers are since z and -z share an orbit
+  Rotation,
+}
+
+// The region as requested. Numbers are kept as text until the float type is known so no precision is lost
+#[derive(Clone, Debug, PartialEq)]
+pub enum Region {
+  Default,
+  Centre { centre: (String, String), zoom: f64 },
+  Bounds([String; 4]),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport<T: Real> {
+  pub width: usize,
+  pub height: usize,
+  grid_x: (T, T),
+  grid_y: (T, T),
+  scale_x: T,
+  scale_y: T,
+  symmetry: Option<Symmetry>,
+}
+
+impl<T: Real> Viewport<T> {
+  pub fn from_bounds(grid_x: (T, T), grid_y: (T, T), width: usize, height: usize, aspect: Aspect) -> Viewport<T> {
+    let (mut grid_x, mut grid_y) = (grid_x, grid_y);
+
+    if aspect != Aspect::Stretch {
+      let span_x = grid_x.1 - grid_x.0;
+      let span_y = grid_y.1 - grid_y.0;
+      let pixel_ratio = T::from_f64(width as f64 / height as f64);
+      let half = T::from_f64(0.5);
+
+      let widen_x = (span_x / span_y < pixel_ratio) == (aspect == Aspect::Fit);
+      if widen_x {
+        let mid_x = (grid_x.0 + grid_x.1) * half;
+        let half_x = span_y * pixel_ratio * half;
+        grid_x = (mid_x - half_x, mid_x + half_x);
+      } else {
+        let mid_y = (grid_y.0 + grid_y.1) * half;
+        let half_y = span_x / pixel_ratio * half;
+        grid_y = (mid_y - half_y, mid_y + half_y);
+      }
+    }
+
+    let scale_x = (grid_x.1 - grid_x.0) / T::from_f64((width.max(2) - 1) as f64);
+    let scale_y = (grid_y.1 - grid_y.0) / T::from_f64((height.max(2) - 1) as f64);
+
+    Viewport {
+      width,
+      height,
+      grid_x,
+      grid_y,
+      scale_x,
+      scale_y,
+      symmetry: None,
+    }
+  }
+
+  // The same region using whichever symmetry of the set it can: the half turn needs the origin at the centre,
+  // the mirror only the real axis across the middle
+  pub fn with_symmetry(mut self, mirrored: bool, half_turn: bool) -> Viewport<T> {
+    let centred_y = is_centred(self.grid_y, self.scale_y);
+
+    self.symmetry = if half_turn && centred_y && is_centred(self.grid_x, self.scale_x) {
+      Some(Symmetry::Rotation)
+    } else if mirrored && centred_y {
+      Some(Symmetry::Mirror)
+    } else {
+      None
+    };
+
+    return self;
+  }
+
+  pub fn from_centre(centre: (T, T), zoom: f64, width: usize, height: usize) -> Viewport<T> {
+    let half_y = ZOOM_HEIGHT / zoom / 2.0;
+    let half_x = T::from_f64(half_y * width as f64 / height as f64);
+    let half_y = T::from_f64(half_y);
+
+    Viewport::from_bounds(
+      (centre.0 - half_x, centre.0 + half_x),
+      (centre.1 - half_y, centre.1 + half_y),
+      width,
+      height,
+      Aspect::Stretch
+    )
+  }
+
+  // Mean pixel width on the complex plane, pixels are only square when the aspect is kept
+  pub fn pixel_size(&self) -> f64 {
+    (self.scale_x.to_f64() + self.scale_y.to_f64()) / 2.0
+  }
+
+  pub fn x0(&self, px: usize) -> T {
+    self.scale_x * T::from_f64(px as f64) + self.grid_x.0
+  }
+
+  // Row 0 is the top of the image, so the imaginary axis runs downwards
+  pub fn y0(&self, py: usize) -> T {
+    self.grid_y.1 - self.scale_y * T::from_f64(py as f64)
+  }
+
+  // The point on the complex plane at a pixel, e.g. to pick the c of a Julia set from a render of the Mandelbrot set
+  pub fn point(&self, px: usize, py: usize) -> (T, T) {
+    (self.x0(px), self.y0(py))
+  }
+
+  // The pixel a point on the complex plane falls in, None outside the image
+  pub fn pixel(&self, x: T, y: T) -> Option<(usize, usize)> {
+    let px = ((x - self.grid_x.0) / self.scale_x).to_f64().round();
+    let py = ((self.grid_y.1 - y) / self.scale_y).to_f64().round();
+
+    if px >= 0.0 && py >= 0.0 && px < self.width as f64 && py < self.height as f64 {
+      Some((px as usize, py as usize))
+    } else {
+      None
+    }
+  }
+
+  // Whether the bottom half of the image repeats the top half, and how
+  pub fn symmetry(&self) -> Option<Symmetry> {
+    self.symmetry
+  }
+
+  // Rows the generators have to compute, the rest follow by symmetry
+  pub fn computed_rows(&self) -> usize {
+    if self.symmetry.is_some() { self.height.div_ceil(2) } else { self.height }
+  }
+}
+
+// Whether an axis runs symmetrically either side of zero, to within a fraction of a pixel
+fn is_centred<T: Real>(grid: (T, T), scale: T) -> bool {
+  (grid.0 + grid.1).abs() <= scale * T::from_f64(1e-3)
+}
+
+impl<T: Real> Default for Viewport<T> {
+  fn default() -> Viewport<T> {
+    const SIZE: usize = 1 << 12;
+
+    Viewport::from_bounds(
+      (T::from_f64(DEFAULT_GRID_X.0), T::from_f64(DEFAULT_GRID_X.1)),
+      (T::from_f64(DEFAULT_GRID_Y.0), T::from_f64(DEFAULT_GRID_Y.1)),
+      SIZE * 3 / 2,
+      SIZE,
+      Aspect::Stretch
+    )
+  }
+}
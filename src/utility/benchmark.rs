@@ -1,4 +1,4 @@
-pub fn benchmark<T>(name: &str, algo: fn() -> T) {
+pub fn benchmark<T>(name: &str, algo: impl Fn() -> T) {
   const ITERATIONS: u32 = 10;
 
   let now = std::time::SystemTime::now();